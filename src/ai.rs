@@ -0,0 +1,204 @@
+use crate::arena::Arena;
+use crate::player::Player;
+use crate::simulation::{Command, Simulation};
+use rand::prelude::*;
+use sdl2::rect::Rect;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn name(&self) -> &str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "easy" => Some(Difficulty::Easy),
+            "normal" => Some(Difficulty::Normal),
+            "hard" => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+    //seconds between decisions, the bot holds its last input in between
+    fn reaction(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.4,
+            Difficulty::Normal => 0.2,
+            Difficulty::Hard => 0.08,
+        }
+    }
+    //chance to pull the trigger when lined up
+    fn accuracy(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.5,
+            Difficulty::Normal => 0.85,
+            Difficulty::Hard => 1.0,
+        }
+    }
+    //chance to jump over a bullet that is about to hit
+    fn dodge(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.15,
+            Difficulty::Normal => 0.5,
+            Difficulty::Hard => 0.85,
+        }
+    }
+    //how close it walks up before standing still to shoot
+    fn range(&self) -> i32 {
+        match self {
+            Difficulty::Easy => 20,
+            Difficulty::Normal => 35,
+            Difficulty::Hard => 45,
+        }
+    }
+}
+
+//drives one player with the same commands a keyboard would produce
+pub struct Bot {
+    pub player: usize,
+    pub difficulty: Difficulty,
+    rng: StdRng,
+    timer: f32,
+    last: Command,
+}
+
+impl Bot {
+    pub fn new(player: usize, difficulty: Difficulty, seed: u64) -> Self {
+        Self {
+            player,
+            difficulty,
+            rng: StdRng::seed_from_u64(seed ^ (player as u64 + 1).wrapping_mul(0x9e37_79b9)),
+            timer: 0.0,
+            last: Command::default(),
+        }
+    }
+    pub fn think(&mut self, sim: &Simulation, dt: f32) -> Command {
+        self.timer -= dt;
+        if self.timer > 0.0 {
+            //keep walking and shooting, but a jump is a single press
+            return Command {
+                jump: false,
+                ..self.last
+            };
+        }
+        self.timer = self.difficulty.reaction();
+        self.last = self.decide(sim);
+        self.last
+    }
+    fn decide(&mut self, sim: &Simulation) -> Command {
+        let mut command = Command::default();
+        let me = &sim.players[self.player];
+        //with no rival left, e.g. in survival, whatever the mode offers to shoot at
+        let foe = nearest_foe(sim, self.player);
+        let prey = foe
+            .map(|foe| foe.entity.rect)
+            .or_else(|| sim.objective.prey(self.player, &sim.players));
+        let theirs = match prey {
+            Some(prey) => prey,
+            None => return command,
+        };
+        let arena = &sim.arena;
+        let mine = me.entity.rect;
+        let dx = theirs.center().x - mine.center().x;
+        let bullet_y = mine.center().y - 1;
+        let lined_up = bullet_y >= theirs.top() && bullet_y < theirs.bottom();
+        let clear = line_clear(arena, mine.center().x, theirs.center().x, bullet_y);
+        let facing = (me.entity.physics.dir > 0.0) == (dx > 0);
+
+        let dy = theirs.center().y - mine.center().y;
+        if dx.abs() < 3 && dy.abs() < 8 {
+            //bullets spawn a few pixels out and would fly past, back off first
+            let right = if dx == 0 { self.rng.gen() } else { dx < 0 };
+            command.right = right;
+            command.left = !right;
+        } else if lined_up && clear {
+            if !facing || dx.abs() > self.difficulty.range() {
+                command.right = dx > 0;
+                command.left = dx < 0;
+            }
+            if !facing {
+                //turning around is a reflex, look again next frame instead of walking into them
+                self.timer = 0.0;
+            }
+            command.shoot = facing && self.rng.gen::<f32>() < self.difficulty.accuracy();
+        } else {
+            //the mode's objective comes first, standing on it is the point
+            let target = sim.objective.goal(self.player, &sim.players).unwrap_or(theirs);
+            if !target.has_intersection(mine) {
+                let dx = target.center().x - mine.center().x;
+                command.right = dx > 2;
+                command.left = dx < -2;
+                //climb towards a foe standing on something higher
+                if target.bottom() < mine.top() {
+                    command.jump = true;
+                }
+            }
+        }
+
+        if foe.is_some_and(|foe| incoming(foe, mine))
+            && self.rng.gen::<f32>() < self.difficulty.dodge()
+        {
+            command.jump = true;
+        }
+        let step = if command.right {
+            2
+        } else if command.left {
+            -2
+        } else {
+            0
+        };
+        if step != 0 {
+            let ahead = Rect::new(mine.x + step, mine.y, mine.width(), mine.height());
+            if arena.tiles.iter().any(|t| ahead.has_intersection(*t)) {
+                command.jump = true;
+            }
+            //hop over water unless the fight is happening in there
+            let below = Rect::new(mine.x + step * 2, mine.bottom(), mine.width(), 2);
+            if !arena.in_water(mine) && !arena.in_water(theirs) && arena.in_water(below) {
+                command.jump = true;
+            }
+        }
+        command
+    }
+}
+
+fn nearest_foe(sim: &Simulation, me: usize) -> Option<&Player> {
+    let pos = sim.players[me].pos();
+    sim.players
+        .iter()
+        .enumerate()
+        .filter(|(i, p)| sim.rules.rivals(me, *i) && !p.is_dead())
+        .min_by(|(_, a), (_, b)| {
+            let da = (a.pos().x - pos.x).abs() + (a.pos().y - pos.y).abs();
+            let db = (b.pos().x - pos.x).abs() + (b.pos().y - pos.y).abs();
+            da.total_cmp(&db)
+        })
+        .map(|(_, p)| p)
+}
+
+//no tile crosses the horizontal line a bullet would travel along
+fn line_clear(arena: &Arena, x0: i32, x1: i32, y: i32) -> bool {
+    let (left, right) = (x0.min(x1), x0.max(x1));
+    !arena.tiles.iter().any(|t| {
+        y >= t.top() && y < t.bottom() && t.left() < right && t.right() > left
+    })
+}
+
+//one of the foe's bullets is at our height, flying at us and close
+fn incoming(foe: &Player, mine: Rect) -> bool {
+    foe.gun.bullets.iter().any(|b| {
+        let gap = mine.center().x as f32 - b.x;
+        b.y as i32 >= mine.top()
+            && (b.y as i32) < mine.bottom()
+            && gap.signum() == b.vel.x.signum()
+            && gap.abs() < 30.0
+    })
+}
@@ -0,0 +1,359 @@
+use crate::creatures::{self, Archetype};
+use crate::data::{self, DataError, Line};
+use crate::pickups::{Item, Spawner};
+use rand::prelude::*;
+use sdl2::rect::Rect;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layer {
+    Back,
+    Middle,
+    Front,
+}
+
+#[derive(Debug, Clone)]
+pub struct Sprite {
+    pub layer: Layer,
+    pub tag: String,
+    pub rect: Rect,
+}
+
+#[derive(Debug, Clone)]
+pub struct Arena {
+    pub name: String,
+    pub bounds: Rect,
+    pub tiles: Vec<Rect>,
+    pub water: Vec<Rect>,
+    pub decor: Vec<(String, (i32, i32))>,
+    pub sprites: Vec<Sprite>,
+    pub grass: Vec<(String, (i32, i32))>,
+    pub spawns: Vec<(i32, i32)>,
+    //creatures from res/creatures.cfg and the height they start at
+    pub wanderers: Vec<(&'static Archetype, i32)>,
+    pub spawners: Vec<Spawner>,
+    //the zone to hold in king of the hill
+    pub hill: Option<Rect>,
+    //where each team's flag sits in capture the flag, team 1 first
+    pub flags: Vec<(i32, i32)>,
+    //where survival waves crawl in from
+    pub lairs: Vec<(i32, i32)>,
+    //tracks looped while playing here, empty for the default `match` playlist
+    pub music: Vec<String>,
+    //the text this arena was parsed from, so replays can carry the exact layout
+    pub source: String,
+}
+
+impl Arena {
+    pub fn load(path: &str) -> Result<Self, DataError> {
+        Self::parse(path, &data::read(path)?)
+    }
+    pub fn parse(path: &str, text: &str) -> Result<Self, DataError> {
+        let mut name = std::path::Path::new(path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut bounds = None;
+        let mut tiles = Vec::new();
+        let mut water = Vec::new();
+        let mut decor = Vec::new();
+        let mut sprites = Vec::new();
+        let mut grass = Vec::new();
+        let mut spawns = Vec::new();
+        let mut wanderers = Vec::new();
+        let mut music = Vec::new();
+        let mut spawners = Vec::new();
+        let mut hill = None;
+        let mut flags = Vec::new();
+        let mut lairs = Vec::new();
+
+        for line in data::lines(text) {
+            match line.key {
+                "name" => {
+                    line.expect_args(path, 1)?;
+                    name = line.args[0].to_string();
+                }
+                "bounds" => {
+                    line.expect_args(path, 4)?;
+                    bounds = Some(rect_at(path, &line, 0)?);
+                }
+                "tile" => {
+                    line.expect_args(path, 4)?;
+                    tiles.push(rect_at(path, &line, 0)?);
+                }
+                "water" => {
+                    line.expect_args(path, 4)?;
+                    water.push(rect_at(path, &line, 0)?);
+                }
+                "decor" => {
+                    line.expect_args(path, 3)?;
+                    decor.push((
+                        line.args[0].to_string(),
+                        (line.num(path, 1)?, line.num(path, 2)?),
+                    ));
+                }
+                "sprite" => {
+                    line.expect_args(path, 6)?;
+                    let layer = match line.args[0] {
+                        "back" => Layer::Back,
+                        "middle" => Layer::Middle,
+                        "front" => Layer::Front,
+                        other => {
+                            return Err(line.error(
+                                path,
+                                format!("unknown layer `{}`, expected back, middle or front", other),
+                            ))
+                        }
+                    };
+                    sprites.push(Sprite {
+                        layer,
+                        tag: line.args[1].to_string(),
+                        rect: rect_at(path, &line, 2)?,
+                    });
+                }
+                "grass" => {
+                    line.expect_args(path, 3)?;
+                    grass.push((
+                        line.args[0].to_string(),
+                        (line.num(path, 1)?, line.num(path, 2)?),
+                    ));
+                }
+                "spawn" => {
+                    line.expect_args(path, 2)?;
+                    spawns.push((line.num(path, 0)?, line.num(path, 1)?));
+                }
+                "wanderer" => {
+                    line.expect_args(path, 2)?;
+                    let kind = match creatures::find(line.args[0]) {
+                        Some(kind) => kind,
+                        None => {
+                            return Err(line.error(
+                                path,
+                                format!("unknown creature `{}`, see res/creatures.cfg", line.args[0]),
+                            ))
+                        }
+                    };
+                    wanderers.push((kind, line.num(path, 1)?));
+                }
+                "pickup" => {
+                    if line.args.len() < 4 {
+                        return Err(line.error(
+                            path,
+                            "`pickup` expects x, y, seconds between items and at least one item"
+                                .to_string(),
+                        ));
+                    }
+                    let mut items = Vec::new();
+                    for name in &line.args[3..] {
+                        match Item::from_name(name) {
+                            Some(item) => items.push(item),
+                            None => {
+                                return Err(line.error(
+                                    path,
+                                    format!("unknown pickup `{}`, expected a weapon, health, speed, damage or shield", name),
+                                ))
+                            }
+                        }
+                    }
+                    spawners.push(Spawner::new(
+                        line.num(path, 0)?,
+                        line.num(path, 1)?,
+                        line.num(path, 2)?,
+                        items,
+                    ));
+                }
+                "hill" => {
+                    line.expect_args(path, 4)?;
+                    hill = Some(rect_at(path, &line, 0)?);
+                }
+                "flag" => {
+                    line.expect_args(path, 2)?;
+                    flags.push((line.num(path, 0)?, line.num(path, 1)?));
+                }
+                "lair" => {
+                    line.expect_args(path, 2)?;
+                    lairs.push((line.num(path, 0)?, line.num(path, 1)?));
+                }
+                "music" => {
+                    if line.args.is_empty() {
+                        return Err(
+                            line.error(path, "`music` expects at least one track".to_string())
+                        );
+                    }
+                    music = line.args.iter().map(|t| t.to_string()).collect();
+                }
+                other => {
+                    return Err(line.error(path, format!("unknown keyword `{}`", other)));
+                }
+            }
+        }
+
+        let bounds = match bounds {
+            Some(bounds) => bounds,
+            None => return Err(DataError::new(path, None, "missing `bounds` line".to_string())),
+        };
+        if spawns.len() < 2 {
+            return Err(DataError::new(
+                path,
+                None,
+                format!("needs at least 2 `spawn` lines, found {}", spawns.len()),
+            ));
+        }
+        Ok(Self {
+            name,
+            bounds,
+            tiles,
+            water,
+            decor,
+            sprites,
+            grass,
+            spawns,
+            wanderers,
+            spawners,
+            hill,
+            flags,
+            lairs,
+            music,
+            source: text.to_string(),
+        })
+    }
+    pub fn in_water(&self, rect: Rect) -> bool {
+        self.water.iter().any(|w| rect.has_intersection(*w))
+    }
+}
+
+//every arena in `path` that parses, the others are reported and left out
+pub fn load_arenas(path: &str) -> Vec<Arena> {
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("{}", DataError::new(path, None, e.to_string()));
+            return Vec::new();
+        }
+    };
+    let mut files: Vec<_> = entries
+        .filter_map(|f| f.ok())
+        .map(|f| f.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "arena"))
+        .collect();
+    files.sort();
+    let mut arenas = Vec::new();
+    for file in files {
+        match Arena::load(file.to_str().unwrap()) {
+            Ok(arena) => arenas.push(arena),
+            Err(e) => eprintln!("skipping arena: {}", e),
+        }
+    }
+    arenas
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArenaChoice {
+    Fixed(usize),
+    Random,
+    Rotate,
+}
+
+pub struct ArenaPicker {
+    pub arenas: Vec<Arena>,
+    pub choice: ArenaChoice,
+    rotation: usize,
+}
+
+impl ArenaPicker {
+    pub fn new(arenas: Vec<Arena>) -> Self {
+        Self {
+            arenas,
+            choice: ArenaChoice::Fixed(0),
+            rotation: 0,
+        }
+    }
+    //cycles through every arena, then `random`, then `rotate`
+    pub fn cycle(&mut self, step: i32) {
+        let count = self.arenas.len() as i32 + 2;
+        let index = match self.choice {
+            ArenaChoice::Fixed(i) => i as i32,
+            ArenaChoice::Random => count - 2,
+            ArenaChoice::Rotate => count - 1,
+        };
+        let index = (index + step).rem_euclid(count);
+        self.choice = if index == count - 2 {
+            ArenaChoice::Random
+        } else if index == count - 1 {
+            ArenaChoice::Rotate
+        } else {
+            ArenaChoice::Fixed(index as usize)
+        };
+    }
+    pub fn label(&self) -> &str {
+        match self.choice {
+            ArenaChoice::Fixed(i) => &self.arenas[i].name,
+            ArenaChoice::Random => "random",
+            ArenaChoice::Rotate => "rotate each round",
+        }
+    }
+    //the arena shown in the menu thumbnail, if the choice is known ahead of time
+    pub fn preview(&self) -> Option<&Arena> {
+        match self.choice {
+            ArenaChoice::Fixed(i) => Some(&self.arenas[i]),
+            ArenaChoice::Random => None,
+            ArenaChoice::Rotate => Some(&self.arenas[self.rotation % self.arenas.len()]),
+        }
+    }
+    //called once per match to get the arenas its rounds take turns on,
+    //a single one unless rotating, then every arena starting where the last match left off
+    pub fn pick(&mut self) -> Vec<Arena> {
+        match self.choice {
+            ArenaChoice::Fixed(i) => vec![self.arenas[i].clone()],
+            ArenaChoice::Random => vec![self.arenas.choose(&mut thread_rng()).unwrap().clone()],
+            ArenaChoice::Rotate => {
+                let start = self.rotation % self.arenas.len();
+                self.rotation += 1;
+                let mut arenas = self.arenas.clone();
+                arenas.rotate_left(start);
+                arenas
+            }
+        }
+    }
+}
+
+fn rect_at(path: &str, line: &Line, start: usize) -> Result<Rect, DataError> {
+    let x: i32 = line.num(path, start)?;
+    let y: i32 = line.num(path, start + 1)?;
+    let w: u32 = line.num(path, start + 2)?;
+    let h: u32 = line.num(path, start + 3)?;
+    if w == 0 || h == 0 {
+        return Err(line.error(path, format!("`{}` needs a non-zero width and height", line.key)));
+    }
+    Ok(Rect::new(x, y, w, h))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_arenas_parse() {
+        let arenas = load_arenas("res/arenas");
+        let files = std::fs::read_dir("res/arenas").unwrap().count();
+        assert_eq!(arenas.len(), files);
+        let jungle = arenas.iter().find(|a| a.name == "jungle").unwrap();
+        assert_eq!(jungle.bounds, Rect::new(0, 0, 97, 59));
+        assert_eq!(jungle.spawns.len(), 4);
+        assert!(jungle.hill.is_some());
+        assert_eq!(jungle.flags.len(), 2);
+    }
+
+    #[test]
+    fn a_missing_arena_folder_is_no_arenas() {
+        assert!(load_arenas("res/no-such-folder").is_empty());
+    }
+
+    #[test]
+    fn bad_lines_name_their_line() {
+        let text = "bounds 0 0 10 10\nspawn 1 1\nspawn 5 1\ntile 0 9 10\n";
+        let e = Arena::parse("test.arena", text).unwrap_err();
+        assert_eq!(e.line, Some(4));
+        let e = Arena::parse("test.arena", "bounds 0 0 10 10\nspawn 1 1\n").unwrap_err();
+        assert_eq!(e.line, None);
+    }
+}
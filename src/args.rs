@@ -0,0 +1,162 @@
+use crate::ai::Difficulty;
+use crate::modes::Mode;
+use crate::simulation::{Rules, MAX_PLAYERS, ROUNDS, ROUND_TIME};
+use rand::prelude::*;
+
+//command line flags, e.g. `fishy_arena --headless --frames 3600 --arena lagoon --seed 42`
+pub struct Args {
+    pub headless: bool,
+    pub frames: u64,
+    pub arena: Option<String>,
+    pub seed: Option<u64>,
+    pub replay: Option<String>,
+    pub record: bool,
+    pub host: bool,
+    pub join: Option<String>,
+    pub port: u16,
+    pub bot: Option<Difficulty>,
+    pub players: usize,
+    pub teams: Option<Vec<usize>>,
+    pub friendly_fire: bool,
+    pub best_of: usize,
+    pub round_time: f32,
+    pub mode: Mode,
+    pub no_audio: bool,
+}
+
+impl Args {
+    pub fn parse() -> Self {
+        let mut args = Self {
+            headless: false,
+            frames: 60 * 60 * 8,
+            arena: None,
+            seed: None,
+            replay: None,
+            record: false,
+            host: false,
+            join: None,
+            port: 7777,
+            bot: None,
+            players: 2,
+            teams: None,
+            friendly_fire: false,
+            best_of: ROUNDS * 2 - 1,
+            round_time: ROUND_TIME,
+            mode: Mode::Deathmatch,
+            no_audio: false,
+        };
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--headless" => args.headless = true,
+                "--record" => args.record = true,
+                "--host" => args.host = true,
+                "--join" => args.join = Some(value(&arg, iter.next())),
+                "--port" => args.port = value(&arg, iter.next()),
+                "--bot" => {
+                    let name: String = value(&arg, iter.next());
+                    args.bot = Difficulty::from_name(&name);
+                    if args.bot.is_none() {
+                        eprintln!("`--bot` expects easy, normal or hard");
+                        std::process::exit(2);
+                    }
+                }
+                "--players" => {
+                    args.players = value(&arg, iter.next());
+                    if !(2..=MAX_PLAYERS).contains(&args.players) {
+                        eprintln!("`--players` expects 2 to {}", MAX_PLAYERS);
+                        std::process::exit(2);
+                    }
+                }
+                //one digit per player, e.g. `--teams 1212`, sets the player count too
+                "--teams" => {
+                    let teams: String = value(&arg, iter.next());
+                    args.teams = teams
+                        .chars()
+                        .map(|c| c.to_digit(10).filter(|d| *d > 0).map(|d| d as usize - 1))
+                        .collect();
+                    if args.teams.is_none() {
+                        eprintln!("`--teams` expects one team digit (1-9) per player, e.g. 1212");
+                        std::process::exit(2);
+                    }
+                }
+                "--friendly-fire" => args.friendly_fire = true,
+                "--mode" => {
+                    let name: String = value(&arg, iter.next());
+                    args.mode = match Mode::from_name(&name) {
+                        Some(mode) => mode,
+                        None => {
+                            eprintln!("`--mode` expects deathmatch, koth, ctf or survival");
+                            std::process::exit(2);
+                        }
+                    };
+                }
+                "--best-of" => {
+                    args.best_of = value(&arg, iter.next());
+                    if args.best_of.is_multiple_of(2) {
+                        eprintln!("`--best-of` expects an odd number of rounds, e.g. 3");
+                        std::process::exit(2);
+                    }
+                }
+                //0 turns the clock and sudden death off
+                "--round-time" => args.round_time = value(&arg, iter.next()),
+                "--no-audio" => args.no_audio = true,
+                "--frames" => args.frames = value(&arg, iter.next()),
+                "--arena" => args.arena = Some(value(&arg, iter.next())),
+                "--seed" => args.seed = Some(value(&arg, iter.next())),
+                "--replay" => args.replay = Some(value(&arg, iter.next())),
+                other => {
+                    eprintln!("unknown argument `{}`", other);
+                    std::process::exit(2);
+                }
+            }
+        }
+        if let Some(teams) = &args.teams {
+            if !(2..=MAX_PLAYERS).contains(&teams.len()) {
+                eprintln!("`--teams` expects 2 to {} players", MAX_PLAYERS);
+                std::process::exit(2);
+            }
+            //everyone on one team would have nobody to fight
+            if teams.iter().all(|t| *t == teams[0]) {
+                eprintln!("`--teams` expects at least two different teams, e.g. 1212");
+                std::process::exit(2);
+            }
+            args.players = teams.len();
+        }
+        args
+    }
+    //the address the join button connects to
+    pub fn peer(&self) -> String {
+        self.join
+            .clone()
+            .unwrap_or_else(|| format!("127.0.0.1:{}", self.port))
+    }
+    pub fn rules(&self) -> Rules {
+        let mut rules = Rules::free_for_all(self.players);
+        if let Some(teams) = &self.teams {
+            rules.teams = teams.clone();
+            rules.friendly_fire = self.friendly_fire;
+        }
+        rules.rounds = self.best_of / 2 + 1;
+        rules.round_time = self.round_time;
+        rules.mode = self.mode;
+        if self.mode == Mode::Survival {
+            rules = Rules::co_op(self.players);
+        }
+        rules
+    }
+    //the seed for the next match, fixed if `--seed` was given
+    pub fn seed(&self) -> u64 {
+        self.seed.unwrap_or_else(|| thread_rng().gen())
+    }
+}
+
+fn value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> T {
+    match value.as_deref().map(str::parse) {
+        Some(Ok(v)) => v,
+        _ => {
+            eprintln!("`{}` expects a value", flag);
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use crate::arena::Arena;
+use crate::data;
+use crate::simulation::{Event, Simulation};
+use rodio::buffer::SamplesBuffer;
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+
+const SFX_DIR: &str = "res/sfx";
+const PLAYLISTS: &str = "res/music.cfg";
+//effects past this many at once cut off the oldest one still playing
+pub const MAX_VOICES: usize = 8;
+//seconds for one playlist to fade into the next
+const CROSSFADE: f32 = 1.0;
+//music drops to this level for a moment on big events, e.g. a death
+const DUCK_LEVEL: f32 = 0.35;
+const DUCK_TIME: f32 = 1.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sfx {
+    Shoot,
+    Hit,
+    Jump,
+    Land,
+    Splash,
+    Death,
+    Pickup,
+}
+
+impl Sfx {
+    pub const ALL: [Sfx; 7] = [
+        Sfx::Shoot,
+        Sfx::Hit,
+        Sfx::Jump,
+        Sfx::Land,
+        Sfx::Splash,
+        Sfx::Death,
+        Sfx::Pickup,
+    ];
+    pub fn name(self) -> &'static str {
+        match self {
+            Sfx::Shoot => "shoot",
+            Sfx::Hit => "hit",
+            Sfx::Jump => "jump",
+            Sfx::Land => "land",
+            Sfx::Splash => "splash",
+            Sfx::Death => "death",
+            Sfx::Pickup => "pickup",
+        }
+    }
+    //round events have no sound of their own, they duck the music instead
+    pub fn of(event: &Event) -> Option<Sfx> {
+        match event {
+            Event::Hit { .. } | Event::Creature { .. } => Some(Sfx::Hit),
+            Event::Shot { .. } => Some(Sfx::Shoot),
+            Event::Jump { .. } => Some(Sfx::Jump),
+            Event::Land { .. } => Some(Sfx::Land),
+            Event::Splash { .. } => Some(Sfx::Splash),
+            Event::Death { .. } => Some(Sfx::Death),
+            Event::Pickup { .. } | Event::Flag { .. } => Some(Sfx::Pickup),
+            Event::RoundOver { .. } | Event::SuddenDeath | Event::Wave { .. } => None,
+        }
+    }
+}
+
+//a decoded effect, copied into a fresh buffer every time it plays
+struct Sound {
+    channels: u16,
+    sample_rate: u32,
+    samples: Vec<i16>,
+}
+
+impl Sound {
+    fn load(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let decoder = Decoder::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+        Ok(Self {
+            channels: decoder.channels(),
+            sample_rate: decoder.sample_rate(),
+            samples: decoder.collect(),
+        })
+    }
+}
+
+fn open_track(path: &str) -> Result<Decoder<BufReader<File>>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    Decoder::new(BufReader::new(file)).map_err(|e| e.to_string())
+}
+
+//loops a playlist in order, fading between playlists when it changes
+struct Music {
+    playlists: HashMap<String, Vec<String>>,
+    playlist: Vec<String>,
+    next: usize,
+    //the playing track and how far it has faded in
+    track: Option<(Sink, f32)>,
+    //tracks of the previous playlist on their way out
+    fading: Vec<(Sink, f32)>,
+    duck: f32,
+    ducked: f32,
+}
+
+impl Music {
+    //`name track track ..` lines, tracks are paths from the game folder
+    fn load_playlists(path: &str) -> HashMap<String, Vec<String>> {
+        let text = match data::read(path) {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("{}", e);
+                return HashMap::new();
+            }
+        };
+        data::lines(&text)
+            .map(|line| {
+                let tracks = line.args.iter().map(|t| t.to_string()).collect();
+                (line.key.to_string(), tracks)
+            })
+            .collect()
+    }
+    fn play(&mut self, tracks: &[String], handle: Option<&OutputStreamHandle>) {
+        if self.playlist == tracks {
+            return;
+        }
+        if let Some(track) = self.track.take() {
+            self.fading.push(track);
+        }
+        self.playlist = tracks.to_vec();
+        self.next = 0;
+        self.start(handle, 0.0);
+    }
+    //starts the next track that opens, giving up after trying each once
+    fn start(&mut self, handle: Option<&OutputStreamHandle>, fade: f32) {
+        //the null backend keeps the playlist but never plays it
+        let handle = match handle {
+            Some(handle) => handle,
+            None => return,
+        };
+        for _ in 0..self.playlist.len() {
+            let path = &self.playlist[self.next];
+            self.next = (self.next + 1) % self.playlist.len();
+            let sink = match Sink::try_new(handle) {
+                Ok(sink) => sink,
+                Err(e) => {
+                    eprintln!("could not play music: {}", e);
+                    break;
+                }
+            };
+            match open_track(path) {
+                Ok(source) => {
+                    sink.set_volume(0.0);
+                    sink.append(source);
+                    self.track = Some((sink, fade));
+                    return;
+                }
+                Err(e) => eprintln!("could not load {}: {}", path, e),
+            }
+        }
+        //nothing playable, stay quiet instead of retrying every frame
+        self.playlist.clear();
+    }
+    fn update(&mut self, dt: f32, volume: f32, handle: Option<&OutputStreamHandle>) {
+        let looped = matches!(&self.track, Some((sink, _)) if sink.empty());
+        if looped {
+            self.track = None;
+            self.start(handle, 1.0);
+        }
+        self.duck -= dt;
+        let level = if self.duck > 0.0 { DUCK_LEVEL } else { 1.0 };
+        self.ducked += (level - self.ducked) * (dt * 8.0).min(1.0);
+        let volume = volume * self.ducked;
+        if let Some((sink, fade)) = &mut self.track {
+            *fade = (*fade + dt / CROSSFADE).min(1.0);
+            sink.set_volume(volume * *fade);
+        }
+        self.fading.retain_mut(|(sink, fade)| {
+            *fade -= dt / CROSSFADE;
+            sink.set_volume(volume * fade.max(0.0));
+            if *fade <= 0.0 {
+                sink.stop();
+            }
+            *fade > 0.0
+        });
+    }
+}
+
+//owns the output stream, the music and every sound effect.
+//without a sound device it runs as a null backend: everything is tracked, nothing is heard
+pub struct Audio {
+    output: Option<(OutputStream, OutputStreamHandle)>,
+    music: Music,
+    voices: Vec<Sink>,
+    sounds: HashMap<Sfx, Sound>,
+    //master volume times the channel's own
+    music_volume: f32,
+    sfx_volume: f32,
+    //the simulation frame whose events were played last
+    heard: Option<u64>,
+}
+
+impl Audio {
+    //`mute` picks the null backend on purpose, e.g. for `--no-audio`
+    pub fn new(mute: bool) -> Self {
+        let output = if mute {
+            None
+        } else {
+            match OutputStream::try_default() {
+                Ok(output) => Some(output),
+                Err(e) => {
+                    eprintln!("warning: no audio device ({}), running without sound", e);
+                    None
+                }
+            }
+        };
+        let mut sounds = HashMap::new();
+        for sfx in Sfx::ALL {
+            let path = format!("{}/{}.wav", SFX_DIR, sfx.name());
+            match Sound::load(&path) {
+                Ok(sound) => {
+                    sounds.insert(sfx, sound);
+                }
+                Err(e) => eprintln!("could not load {}: {}", path, e),
+            }
+        }
+        Self {
+            output,
+            music: Music {
+                playlists: Music::load_playlists(PLAYLISTS),
+                playlist: Vec::new(),
+                next: 0,
+                track: None,
+                fading: Vec::new(),
+                duck: 0.0,
+                ducked: 1.0,
+            },
+            voices: Vec::new(),
+            sounds,
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            heard: None,
+        }
+    }
+    pub fn set_volume(&mut self, master: f32, music: f32, sfx: f32) {
+        self.music_volume = master * music;
+        self.sfx_volume = master * sfx;
+        for voice in &self.voices {
+            voice.set_volume(self.sfx_volume);
+        }
+    }
+    //one of the lists in res/music.cfg, the current one keeps playing if it is the same
+    pub fn play_list(&mut self, name: &str) {
+        match self.music.playlists.get(name) {
+            Some(tracks) => {
+                let tracks = tracks.clone();
+                self.music
+                    .play(&tracks, self.output.as_ref().map(|(_, h)| h));
+            }
+            None => eprintln!("no playlist named `{}` in {}", name, PLAYLISTS),
+        }
+    }
+    //an arena's own `music` line, or the `match` list
+    pub fn play_arena(&mut self, arena: &Arena) {
+        if arena.music.is_empty() {
+            self.play_list("match");
+        } else {
+            self.music
+                .play(&arena.music, self.output.as_ref().map(|(_, h)| h));
+        }
+    }
+    //quiets the music for a moment so a big event stands out
+    pub fn duck(&mut self) {
+        self.music.duck = DUCK_TIME;
+    }
+    //fades, loops and ducks the music, once a frame
+    pub fn update(&mut self, dt: f32) {
+        self.music
+            .update(dt, self.music_volume, self.output.as_ref().map(|(_, h)| h));
+    }
+    pub fn play(&mut self, sfx: Sfx) {
+        let (sound, handle) = match (self.sounds.get(&sfx), &self.output) {
+            (Some(sound), Some((_, handle))) => (sound, handle),
+            _ => return,
+        };
+        self.voices.retain(|voice| !voice.empty());
+        if self.voices.len() >= MAX_VOICES {
+            self.voices.remove(0).stop();
+        }
+        let voice = match Sink::try_new(handle) {
+            Ok(voice) => voice,
+            Err(e) => {
+                eprintln!("could not play {}: {}", sfx.name(), e);
+                return;
+            }
+        };
+        voice.set_volume(self.sfx_volume);
+        voice.append(SamplesBuffer::new(
+            sound.channels,
+            sound.sample_rate,
+            sound.samples.clone(),
+        ));
+        self.voices.push(voice);
+    }
+    //plays what the last simulated frame produced, once, however often it is drawn
+    pub fn react(&mut self, sim: &Simulation) {
+        if self.heard == Some(sim.frame) {
+            return;
+        }
+        self.heard = Some(sim.frame);
+        for event in &sim.events {
+            if let Some(sfx) = Sfx::of(event) {
+                self.play(sfx);
+            }
+            if let Event::Death { .. }
+            | Event::RoundOver { .. }
+            | Event::SuddenDeath
+            | Event::Wave { .. } = event
+            {
+                self.duck();
+            }
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::draw;
 use crate::math::Vec2;
 use crate::rect;
 use rand::prelude::*;
@@ -16,8 +17,7 @@ pub struct Particle {
 }
 
 impl Particle {
-    pub fn new(x: f32, y: f32) -> Self {
-        let mut rng = thread_rng();
+    pub fn new(x: f32, y: f32, rng: &mut StdRng) -> Self {
         Self {
             alpha: 255.0,
             acceleration: Vec2::new(rng.gen_range(8.0..16.0), rng.gen_range(8.0..16.0)),
@@ -25,7 +25,20 @@ impl Particle {
             y,
         }
     }
+    //flies away from whatever `vel` ran into, `acceleration` is its velocity here
+    pub fn spark(pos: Vec2, vel: Vec2, rng: &mut StdRng) -> Self {
+        Self {
+            alpha: 255.0,
+            acceleration: Vec2::new(
+                -vel.x.signum() * rng.gen_range(10.0..30.0),
+                rng.gen_range(-20.0..20.0),
+            ),
+            x: pos.x,
+            y: pos.y,
+        }
+    }
 }
+#[derive(Clone)]
 pub struct Physics {
     pub pos: Vec2,
     pub vel: Vec2,
@@ -43,7 +56,9 @@ impl Physics {
     }
 }
 
+#[derive(Clone)]
 pub struct Stat(pub f32, pub f32);
+#[derive(Clone)]
 pub struct Animator {
     pub frame: Rect,
     pub count: i32,
@@ -112,17 +127,17 @@ impl Decor {
         cache: &mut HashMap<String, Texture<'a>>,
         offset: (i32, i32),
     ) {
-        display
-            .copy(
-                cache.get_mut(&self.tag).unwrap(),
-                self.animator.frame,
-                rect!(
-                    self.pos.0 - offset.0,
-                    self.pos.1 - offset.1,
-                    self.animator.frame.w,
-                    self.animator.frame.h
-                ),
-            )
-            .unwrap();
+        draw::texture(
+            display,
+            cache,
+            &self.tag,
+            Some(self.animator.frame),
+            rect!(
+                self.pos.0 - offset.0,
+                self.pos.1 - offset.1,
+                self.animator.frame.w,
+                self.animator.frame.h
+            ),
+        );
     }
 }
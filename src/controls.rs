@@ -0,0 +1,183 @@
+use crate::data::{self, DataError};
+use crate::input::Input;
+use crate::simulation::{Command, MAX_PLAYERS};
+use sdl2::controller::Button;
+use sdl2::keyboard::Keycode;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Left,
+    Right,
+    Jump,
+    Shoot,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 5] = [
+        Action::Left,
+        Action::Right,
+        Action::Jump,
+        Action::Shoot,
+        Action::Pause,
+    ];
+    pub fn name(&self) -> &str {
+        match self {
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Jump => "jump",
+            Action::Shoot => "shoot",
+            Action::Pause => "pause",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.name() == name)
+    }
+}
+
+//which key every player uses for every action, stored as `p1 jump W` lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct Controls {
+    keys: [[Keycode; Action::ALL.len()]; MAX_PLAYERS],
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        use Keycode::*;
+        Self {
+            keys: [
+                [A, D, W, S, P],
+                [Left, Right, Up, Down, Return],
+                [J, L, I, K, O],
+                [Kp4, Kp6, Kp8, Kp5, KpEnter],
+            ],
+        }
+    }
+}
+
+impl Controls {
+    //a missing file means defaults, bad lines are reported and skipped
+    pub fn load(path: &Path) -> Self {
+        let mut controls = Self::default();
+        let path = path.to_string_lossy();
+        let text = match std::fs::read_to_string(path.as_ref()) {
+            Ok(text) => text,
+            Err(_) => return controls,
+        };
+        for line in data::lines(&text) {
+            if let Err(e) = controls.parse_line(&path, &line) {
+                eprintln!("{}", e);
+            }
+        }
+        for player in 0..MAX_PLAYERS {
+            for action in Action::ALL {
+                if let Some((other, other_action)) = controls.conflict(player, action) {
+                    if (other, other_action as usize) > (player, action as usize) {
+                        eprintln!(
+                            "{}: p{} {} and p{} {} are both bound to {}",
+                            path,
+                            player + 1,
+                            action.name(),
+                            other + 1,
+                            other_action.name(),
+                            controls.key(player, action).name()
+                        );
+                    }
+                }
+            }
+        }
+        controls
+    }
+    fn parse_line(&mut self, path: &str, line: &data::Line) -> Result<(), DataError> {
+        let player = line
+            .key
+            .strip_prefix('p')
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|n| (1..=MAX_PLAYERS).contains(n))
+            .ok_or_else(|| line.error(path, format!("unknown player `{}`", line.key)))?;
+        if line.args.len() < 2 {
+            return Err(line.error(path, "expects an action and a key".to_string()));
+        }
+        let action = Action::from_name(line.args[0])
+            .ok_or_else(|| line.error(path, format!("unknown action `{}`", line.args[0])))?;
+        //sdl key names may contain spaces, e.g. `Keypad 4`
+        let name = line.args[1..].join(" ");
+        let key = Keycode::from_name(&name)
+            .ok_or_else(|| line.error(path, format!("unknown key `{}`", name)))?;
+        self.bind(player - 1, action, key);
+        Ok(())
+    }
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut text = String::from("# `player action key`, keys use sdl names\n");
+        for player in 0..MAX_PLAYERS {
+            for action in Action::ALL {
+                text += &format!(
+                    "p{} {} {}\n",
+                    player + 1,
+                    action.name(),
+                    self.key(player, action).name()
+                );
+            }
+        }
+        std::fs::write(path, text)
+    }
+    pub fn key(&self, player: usize, action: Action) -> Keycode {
+        self.keys[player][action as usize]
+    }
+    pub fn bind(&mut self, player: usize, action: Action, key: Keycode) {
+        self.keys[player][action as usize] = key;
+    }
+    //another binding sharing this one's key, if any
+    pub fn conflict(&self, player: usize, action: Action) -> Option<(usize, Action)> {
+        let key = self.key(player, action);
+        (0..MAX_PLAYERS)
+            .flat_map(|p| Action::ALL.into_iter().map(move |a| (p, a)))
+            .find(|(p, a)| (*p, *a) != (player, action) && self.key(*p, *a) == key)
+    }
+    pub fn command(&self, input: &Input, player: usize) -> Command {
+        Command {
+            right: input.is_key_pressed(self.key(player, Action::Right)),
+            left: input.is_key_pressed(self.key(player, Action::Left)),
+            jump: input.is_key_clicked(self.key(player, Action::Jump)),
+            shoot: input.is_key_pressed(self.key(player, Action::Shoot)),
+        }
+    }
+    //escape and the start button always pause, next to everyone's own pause key
+    pub fn paused(&self, input: &Input) -> bool {
+        input.is_key_clicked(Keycode::Escape)
+            || (0..MAX_PLAYERS).any(|p| {
+                input.is_key_clicked(self.key(p, Action::Pause))
+                    || input.is_pad_clicked(p, Button::Start)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_controls_load_back_the_same() {
+        let path = std::env::temp_dir().join(format!("controls-{}.cfg", std::process::id()));
+        let mut controls = Controls::default();
+        controls.bind(0, Action::Jump, Keycode::Space);
+        controls.bind(2, Action::Shoot, Keycode::Kp0);
+        controls.save(&path).unwrap();
+        let loaded = Controls::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, controls);
+    }
+
+    #[test]
+    fn two_players_on_one_key_conflict() {
+        let mut controls = Controls::default();
+        assert_eq!(controls.conflict(0, Action::Jump), None);
+        controls.bind(1, Action::Shoot, Keycode::W);
+        assert_eq!(controls.conflict(0, Action::Jump), Some((1, Action::Shoot)));
+        assert_eq!(controls.conflict(1, Action::Shoot), Some((0, Action::Jump)));
+    }
+}
@@ -0,0 +1,199 @@
+use crate::data::{self, DataError};
+use std::sync::OnceLock;
+
+const CREATURES: &str = "res/creatures.cfg";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Behaviour {
+    //walks along the ground and turns around at walls
+    Patrol,
+    //patrols, but runs the other way from a player that comes close
+    Flee,
+    //stays inside the water it was put in
+    Swim,
+    //no gravity, drifts up and down as it goes
+    Fly,
+    //patrols and jumps every now and then
+    Hop,
+}
+
+impl Behaviour {
+    pub fn from_name(name: &str) -> Option<Behaviour> {
+        match name {
+            "patrol" => Some(Behaviour::Patrol),
+            "flee" => Some(Behaviour::Flee),
+            "swim" => Some(Behaviour::Swim),
+            "fly" => Some(Behaviour::Fly),
+            "hop" => Some(Behaviour::Hop),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Archetype {
+    pub name: String,
+    //texture from res/textures, a strip of animation frames side by side
+    pub sprite: String,
+    //one frame, also the creature's size in arena pixels
+    pub size: (u32, u32),
+    pub frames: i32,
+    //seconds per frame
+    pub frame_time: f32,
+    //arena pixels per second, each creature picks a speed in this range
+    pub speed: (f32, f32),
+    pub hp: f32,
+    pub behaviour: Behaviour,
+}
+
+//the caterpillar that used to be the only creature
+impl Default for Archetype {
+    fn default() -> Self {
+        Self {
+            name: "caterpillar".to_string(),
+            sprite: "caterpillar".to_string(),
+            size: (3, 2),
+            frames: 3,
+            frame_time: 0.2,
+            speed: (2.0, 4.0),
+            hp: 2.0,
+            behaviour: Behaviour::Patrol,
+        }
+    }
+}
+
+struct Bestiary {
+    creatures: Vec<Archetype>,
+    //of the file they came from, 0 when it could not be used
+    fingerprint: u64,
+}
+
+fn loaded() -> &'static Bestiary {
+    static BESTIARY: OnceLock<Bestiary> = OnceLock::new();
+    BESTIARY.get_or_init(|| {
+        let bestiary = data::read(CREATURES).and_then(|text| {
+            Ok(Bestiary {
+                creatures: parse(CREATURES, &text)?,
+                fingerprint: data::fingerprint(&text),
+            })
+        });
+        bestiary.unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            Bestiary {
+                creatures: vec![Archetype::default()],
+                fingerprint: 0,
+            }
+        })
+    })
+}
+
+//every creature in res/creatures.cfg, read once
+pub fn bestiary() -> &'static [Archetype] {
+    &loaded().creatures
+}
+
+//which creatures.cfg this game plays with, replays recorded with another one would desync
+pub fn fingerprint() -> u64 {
+    loaded().fingerprint
+}
+
+pub fn find(name: &str) -> Option<&'static Archetype> {
+    bestiary().iter().find(|c| c.name == name)
+}
+
+//`creature name` starts a creature, the lines after it set it up, anything left out keeps the caterpillar's
+pub fn parse(path: &str, text: &str) -> Result<Vec<Archetype>, DataError> {
+    let mut creatures: Vec<Archetype> = Vec::new();
+    for line in data::lines(text) {
+        if line.key == "creature" {
+            line.expect_args(path, 1)?;
+            creatures.push(Archetype {
+                name: line.args[0].to_string(),
+                ..Archetype::default()
+            });
+            continue;
+        }
+        let creature = match creatures.last_mut() {
+            Some(creature) => creature,
+            None => {
+                return Err(line.error(path, format!("`{}` before any `creature` line", line.key)))
+            }
+        };
+        match line.key {
+            "size" => {
+                line.expect_args(path, 2)?;
+                creature.size = (line.num(path, 0)?, line.num(path, 1)?);
+                if creature.size.0 == 0 || creature.size.1 == 0 {
+                    return Err(line.error(path, "`size` can't be 0".to_string()));
+                }
+                continue;
+            }
+            "frames" => {
+                line.expect_args(path, 2)?;
+                creature.frames = line.num::<i32>(path, 0)?.max(1);
+                creature.frame_time = line.num(path, 1)?;
+                continue;
+            }
+            "speed" => {
+                line.expect_args(path, 2)?;
+                creature.speed = (line.num(path, 0)?, line.num(path, 1)?);
+                if creature.speed.0 > creature.speed.1 {
+                    return Err(line.error(path, "`speed` expects the slowest first".to_string()));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        line.expect_args(path, 1)?;
+        match line.key {
+            "sprite" => creature.sprite = line.args[0].to_string(),
+            "hp" => creature.hp = line.num(path, 0)?,
+            "behaviour" => {
+                creature.behaviour = match Behaviour::from_name(line.args[0]) {
+                    Some(behaviour) => behaviour,
+                    None => {
+                        return Err(line.error(
+                            path,
+                            format!(
+                                "unknown behaviour `{}`, expected patrol, flee, swim, fly or hop",
+                                line.args[0]
+                            ),
+                        ))
+                    }
+                }
+            }
+            other => return Err(line.error(path, format!("unknown keyword `{}`", other))),
+        }
+    }
+    if creatures.is_empty() {
+        return Err(DataError::new(
+            path,
+            None,
+            "no `creature` lines".to_string(),
+        ));
+    }
+    Ok(creatures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_creatures_parse() {
+        let creatures = parse(CREATURES, &data::read(CREATURES).unwrap()).unwrap();
+        assert!(creatures.iter().any(|c| c.name == "caterpillar"));
+        assert!(creatures.iter().any(|c| c.behaviour == Behaviour::Swim));
+    }
+
+    #[test]
+    fn bad_lines_name_their_line() {
+        let text = "creature bat\nsize 3 2\nbehaviour dive\n";
+        let e = parse("test.cfg", text).unwrap_err();
+        assert_eq!(e.line, Some(3));
+        let e = parse("test.cfg", "size 3 2\n").unwrap_err();
+        assert_eq!(e.line, Some(1));
+        let e = parse("test.cfg", "# nothing here\n").unwrap_err();
+        assert_eq!(e.line, None);
+    }
+}
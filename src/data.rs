@@ -0,0 +1,109 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Debug, Clone)]
+pub struct DataError {
+    pub path: String,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl DataError {
+    pub fn new(path: &str, line: Option<usize>, message: String) -> Self {
+        Self {
+            path: path.to_string(),
+            line,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path, line, self.message),
+            None => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+pub struct Line<'a> {
+    pub number: usize,
+    pub key: &'a str,
+    pub args: Vec<&'a str>,
+}
+
+impl<'a> Line<'a> {
+    pub fn error(&self, path: &str, message: String) -> DataError {
+        DataError::new(path, Some(self.number), message)
+    }
+    pub fn expect_args(&self, path: &str, count: usize) -> Result<(), DataError> {
+        if self.args.len() != count {
+            return Err(self.error(
+                path,
+                format!(
+                    "`{}` expects {} values, found {}",
+                    self.key,
+                    count,
+                    self.args.len()
+                ),
+            ));
+        }
+        Ok(())
+    }
+    pub fn num<T: FromStr>(&self, path: &str, index: usize) -> Result<T, DataError> {
+        let arg = self.args[index];
+        arg.parse::<T>().map_err(|_| {
+            self.error(
+                path,
+                format!("`{}` value #{} is not a valid number: `{}`", self.key, index + 1, arg),
+            )
+        })
+    }
+}
+
+//splits a data file into `key arg arg ..` lines, skipping blanks and `#` comments
+pub fn lines(text: &str) -> impl Iterator<Item = Line<'_>> {
+    text.lines().enumerate().filter_map(|(i, raw)| {
+        let content = raw.split('#').next().unwrap_or("");
+        let mut words = content.split_whitespace();
+        let key = words.next()?;
+        Some(Line {
+            number: i + 1,
+            key,
+            args: words.collect(),
+        })
+    })
+}
+
+//identifies what a data file says, comments and spacing left out. fnv-1a, so it is the
+//same on every machine and build, unlike std's hasher
+pub fn fingerprint(text: &str) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for line in lines(text) {
+        for word in std::iter::once(line.key).chain(line.args) {
+            for byte in word.bytes().chain(std::iter::once(b' ')) {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+        hash ^= b'\n' as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+pub fn read(path: &str) -> Result<String, DataError> {
+    std::fs::read_to_string(path).map_err(|e| DataError::new(path, None, e.to_string()))
+}
+
+//where per-user files like key bindings live, e.g. ~/.config/gladarena/controls.cfg
+pub fn config_path(file: &str) -> PathBuf {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default();
+    base.join("gladarena").join(file)
+}
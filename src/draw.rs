@@ -0,0 +1,360 @@
+use crate::arena::Arena;
+use crate::entities::Wanderer;
+use crate::modes::{CaptureTheFlag, KingOfTheHill, View, HILL_TIME};
+use crate::pickups::Power;
+use crate::player::Player;
+use crate::simulation::Simulation;
+use crate::survival::Survival;
+use crate::ui::{text, TextCache};
+use crate::{point, rect};
+use sdl2::{
+    pixels::Color,
+    rect::{Point, Rect},
+    render::{BlendMode, Texture, WindowCanvas},
+};
+use std::collections::HashMap;
+
+//how the simulation's pieces look. the simulation itself never draws, so this is all on the sdl side
+
+pub const TEAM_COLORS: [Color; 4] = [
+    Color::RGB(139, 181, 212),
+    Color::RGB(212, 139, 139),
+    Color::RGB(228, 219, 160),
+    Color::RGB(150, 204, 143),
+];
+
+pub fn team_color(team: usize) -> Color {
+    TEAM_COLORS[team % TEAM_COLORS.len()]
+}
+
+//copies the texture `tag` into `to`, one missing from res/textures shows up as a plain block
+pub fn texture<'a>(
+    display: &mut WindowCanvas,
+    cache: &mut HashMap<String, Texture<'a>>,
+    tag: &str,
+    from: Option<Rect>,
+    to: Rect,
+) {
+    match cache.get_mut(tag) {
+        Some(texture) => {
+            display.copy(texture, from, to).unwrap();
+        }
+        None => {
+            display.set_draw_color(Color::RGB(212, 196, 139));
+            display.fill_rect(to).unwrap();
+        }
+    }
+}
+
+pub fn player<'a>(
+    display: &mut WindowCanvas,
+    cache: &mut HashMap<String, Texture<'a>>,
+    texts: &mut TextCache,
+    player: &Player,
+    offset: (i32, i32),
+) {
+    //boosted bullets show up red
+    display.set_draw_color(if player.has(Power::Damage) {
+        Color::RGB(212, 139, 139)
+    } else {
+        Color::WHITE
+    });
+    for bullet in &player.gun.bullets {
+        display.draw_point(point!(
+            bullet.x - offset.0 as f32,
+            bullet.y - offset.1 as f32
+        ));
+    }
+    display.set_blend_mode(BlendMode::Blend);
+
+    for particle in &player.trail {
+        display.set_draw_color(Color::RGBA(255, 255, 255, particle.alpha as u8));
+        display.draw_point(point!(
+            particle.x - offset.0 as f32,
+            particle.y - offset.1 as f32
+        ));
+    }
+    for spark in &player.sparks {
+        display.set_draw_color(Color::RGBA(228, 219, 160, spark.alpha as u8));
+        display
+            .draw_point(point!(spark.x - offset.0 as f32, spark.y - offset.1 as f32))
+            .unwrap();
+    }
+    display.set_blend_mode(BlendMode::None);
+
+    text(
+        display,
+        texts,
+        &player.title,
+        Color::RGB(228, 219, 160),
+        (player.entity.rect.x + player.entity.rect.w / 2) - offset.0,
+        (player.entity.rect.y - 2) - offset.1,
+        0.25,
+    );
+    if player.entity.hp.0 != player.entity.hp.1 {
+        display.set_draw_color(Color::WHITE);
+        display
+            .draw_rect(rect!(
+                player.entity.rect.x - offset.0,
+                player.entity.rect.y - 4 - offset.1,
+                player.entity.hp.1,
+                1
+            ))
+            .unwrap();
+        if !(player.entity.hp.0 <= 0.0) {
+            display.set_draw_color(Color::GREEN);
+
+            display
+                .draw_rect(rect!(
+                    player.entity.rect.x - offset.0,
+                    player.entity.rect.y - 4 - offset.1,
+                    player.entity.hp.0,
+                    1
+                ))
+                .unwrap();
+        }
+    }
+
+    //blinks while it can't be hurt
+    if (player.invulnerable * 12.0) as i32 % 2 == 0 {
+        display.set_draw_color(team_color(player.team));
+        display.fill_rect(rect!(
+            player.entity.rect.x - offset.0,
+            player.entity.rect.y - offset.1,
+            player.entity.rect.w,
+            player.entity.rect.h
+        ));
+    }
+    if player.has(Power::Shield) {
+        display.set_blend_mode(BlendMode::Blend);
+        display.set_draw_color(Color::RGBA(139, 181, 212, 160));
+        display
+            .draw_rect(rect!(
+                player.entity.rect.x - 1 - offset.0,
+                player.entity.rect.y - 1 - offset.1,
+                player.entity.rect.w + 2,
+                player.entity.rect.h + 2
+            ))
+            .unwrap();
+        display.set_blend_mode(BlendMode::None);
+    }
+    //weapons without a texture of their own hold the plain gun
+    let sprite = if cache.contains_key(&player.gun.weapon.sprite) {
+        &player.gun.weapon.sprite
+    } else {
+        "gun"
+    };
+    let texture = cache.get_mut(sprite).unwrap();
+    let size = texture.query();
+    //the grip stays in the hand, longer guns reach further out
+    let x = if player.entity.physics.dir < 0.0 {
+        player.entity.rect.x - size.width as i32 + 3
+    } else {
+        player.entity.rect.x + 4
+    };
+    display
+        .copy_ex(
+            texture,
+            None,
+            Rect::new(
+                x - offset.0,
+                ((player.entity.rect.y + player.entity.rect.h / 2) - 1) - offset.1,
+                size.width,
+                size.height,
+            ),
+            0.0,
+            None,
+            player.entity.physics.dir < 0.0,
+            false,
+        )
+        .unwrap();
+}
+
+pub fn creature<'a>(
+    display: &mut WindowCanvas,
+    cache: &mut HashMap<String, Texture<'a>>,
+    creature: &Wanderer,
+    offset: (i32, i32),
+) {
+    let rect = rect!(
+        creature.entity.rect.x - offset.0,
+        creature.entity.rect.y - offset.1,
+        creature.entity.rect.w,
+        creature.entity.rect.h
+    );
+    //a sprite missing from res/textures shows up as a plain block, red when it hunts players
+    let texture = match cache.get_mut(&creature.kind.sprite) {
+        Some(texture) => texture,
+        None => {
+            display.set_draw_color(if creature.hostile {
+                Color::RGB(212, 139, 139)
+            } else {
+                Color::RGB(150, 204, 143)
+            });
+            display.fill_rect(rect).unwrap();
+            return;
+        }
+    };
+    if creature.hostile {
+        texture.set_color_mod(255, 120, 120);
+    }
+    display
+        .copy_ex(
+            texture,
+            creature.entity.animator.frame,
+            rect,
+            0.0,
+            None,
+            creature.entity.physics.dir < 0.0,
+            false,
+        )
+        .unwrap();
+    texture.set_color_mod(255, 255, 255);
+}
+
+//the objectives in the arena, e.g. the hill, the flags or the survival creatures
+pub fn objective<'a>(
+    display: &mut WindowCanvas,
+    cache: &mut HashMap<String, Texture<'a>>,
+    view: View,
+    offset: (i32, i32),
+) {
+    match view {
+        View::Nothing => {}
+        View::Hill(koth) => hill(display, koth, offset),
+        View::Flags(ctf) => flags(display, ctf, offset),
+        View::Waves(survival) => {
+            for bug in &survival.creatures {
+                creature(display, cache, bug, offset);
+            }
+        }
+    }
+}
+
+fn hill(display: &mut WindowCanvas, koth: &KingOfTheHill, offset: (i32, i32)) {
+    let hill = match koth.hill {
+        Some(hill) => hill,
+        None => return,
+    };
+    let rect = rect!(hill.x - offset.0, hill.y - offset.1, hill.w, hill.h);
+    //the leading team's color, grey while nobody has held it
+    let lead = koth
+        .held
+        .iter()
+        .enumerate()
+        .filter(|(_, held)| **held > 0.0)
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(team, _)| team);
+    let color = lead.map_or(Color::RGB(168, 181, 174), team_color);
+    display.set_blend_mode(BlendMode::Blend);
+    display.set_draw_color(Color::RGBA(color.r, color.g, color.b, 40));
+    display.fill_rect(rect).unwrap();
+    display.set_draw_color(Color::RGBA(color.r, color.g, color.b, 140));
+    display.draw_rect(rect).unwrap();
+    display.set_blend_mode(BlendMode::None);
+}
+
+fn flags(display: &mut WindowCanvas, ctf: &CaptureTheFlag, offset: (i32, i32)) {
+    for flag in &ctf.flags {
+        let (x, y) = (flag.pos.0 - offset.0, flag.pos.1 - offset.1);
+        display.set_draw_color(Color::RGB(168, 181, 174));
+        display.fill_rect(rect!(x, y, 1, 5)).unwrap();
+        display.set_draw_color(team_color(flag.team));
+        display.fill_rect(rect!(x + 1, y, 2, 2)).unwrap();
+    }
+}
+
+//the mode's line under the round and clock, `dim` is the world view size
+pub fn objective_hud(
+    sim: &Simulation,
+    display: &mut WindowCanvas,
+    texts: &mut TextCache,
+    dim: (i32, i32),
+) {
+    let line = match sim.objective.view() {
+        View::Nothing => return,
+        View::Hill(koth) => match hill_line(sim, koth) {
+            Some(line) => line,
+            None => return,
+        },
+        View::Flags(ctf) => flags_line(sim, ctf),
+        View::Waves(survival) => waves_line(survival),
+    };
+    let light = Color::RGB(241, 234, 182);
+    text(display, texts, &line, light, dim.0 / 2, 10, 0.25);
+}
+
+fn hill_line(sim: &Simulation, koth: &KingOfTheHill) -> Option<String> {
+    koth.hill?;
+    let line: Vec<String> = (0..koth.held.len())
+        .filter(|team| sim.rules.teams.contains(team))
+        .map(|team| {
+            format!(
+                "{} {}/{}",
+                sim.side_name(team),
+                koth.held[team] as i32,
+                HILL_TIME
+            )
+        })
+        .collect();
+    Some(format!("hill  {}", line.join("  ")))
+}
+
+fn flags_line(sim: &Simulation, ctf: &CaptureTheFlag) -> String {
+    let line: Vec<String> = ctf
+        .flags
+        .iter()
+        .map(|flag| {
+            let state = match flag.carrier {
+                Some(_) => "taken",
+                None if flag.at_home() => "home",
+                None => "dropped",
+            };
+            format!("{} {}", sim.side_name(flag.team), state)
+        })
+        .collect();
+    format!("flags  {}", line.join("  "))
+}
+
+fn waves_line(survival: &Survival) -> String {
+    if survival.wave == 0 {
+        "get ready".to_string()
+    } else {
+        let left = survival.creatures.len() + survival.pending;
+        format!(
+            "wave {}  {} left  {} kills",
+            survival.wave, left, survival.kills
+        )
+    }
+}
+
+//the arena's own tiles and water scaled down into `frame`, for the menu thumbnail
+pub fn arena_preview(display: &mut WindowCanvas, arena: &Arena, frame: Rect) {
+    let sx = frame.w as f32 / arena.bounds.w as f32;
+    let sy = frame.h as f32 / arena.bounds.h as f32;
+    let scale = |r: &Rect| {
+        rect!(
+            frame.x + ((r.x - arena.bounds.x) as f32 * sx) as i32,
+            frame.y + ((r.y - arena.bounds.y) as f32 * sy) as i32,
+            ((r.w as f32 * sx) as u32).max(1),
+            ((r.h as f32 * sy) as u32).max(1)
+        )
+    };
+    display.set_draw_color(Color::RGB(146, 146, 156));
+    for water in &arena.water {
+        display.fill_rect(scale(water)).unwrap();
+    }
+    display.set_draw_color(Color::RGB(168, 181, 174));
+    for tile in &arena.tiles {
+        display.fill_rect(scale(tile)).unwrap();
+    }
+    display.set_draw_color(Color::RGB(139, 181, 212));
+    for spawn in &arena.spawns {
+        display
+            .draw_point(point!(
+                frame.x as f32 + (spawn.0 - arena.bounds.x) as f32 * sx,
+                frame.y as f32 + (spawn.1 - arena.bounds.y) as f32 * sy
+            ))
+            .unwrap();
+    }
+}
@@ -1,237 +1,275 @@
+use crate::ai::{Bot, Difficulty};
+use crate::arena::{Arena, Layer};
 use crate::components::*;
-use crate::entities::Wanderer;
+use crate::controls::Controls;
+use crate::draw;
 use crate::input::Input;
 use crate::math::*;
-use crate::player::Player;
+use crate::replay::Replay;
+use crate::simulation::{Command, Event, Rules, Simulation, MAX_PLAYERS};
+use crate::ui::{text, TextCache};
 use crate::{point, rect};
-use rand::prelude::*;
 use sdl2::{
-    keyboard::Keycode,
+    controller::{Axis, Button},
     pixels::Color,
     rect::{Point, Rect},
     render::{BlendMode, Texture, WindowCanvas},
 };
 use std::collections::HashMap;
+
+//the sdl side of a match: reads the keyboard, drives the simulation and draws it
 pub struct Ecosystem {
-    bubbles: Vec<Particle>,
+    pub sim: Simulation,
+    pub replay: Replay,
+    pub bots: Vec<Bot>,
     decor: Vec<Decor>,
-    timer: f32,
-    tiles: Vec<Rect>,
-    pub players: Vec<Player>,
+    //the arena the decor belongs to, rotating matches swap it between rounds
+    decor_arena: String,
+    //seconds left on screen and the text, shown along the bottom
     messages: Vec<(f32, String)>,
-    bugs: [Wanderer; 2],
+}
+
+//what it takes to play the same match again from the start, against the same bots
+pub struct Rematch {
+    arenas: Vec<Arena>,
+    seed: u64,
+    rules: Rules,
+    bots: Vec<(usize, Difficulty)>,
+}
+impl Rematch {
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    pub fn start(&self) -> Ecosystem {
+        let mut ecosystem = Ecosystem::new(self.arenas.clone(), self.seed, self.rules.clone());
+        for (player, difficulty) in &self.bots {
+            ecosystem.bots.push(Bot::new(*player, *difficulty, self.seed));
+        }
+        ecosystem
+    }
+}
+
+fn decor_of(arena: &Arena) -> Vec<Decor> {
+    arena
+        .decor
+        .iter()
+        .map(|(tag, pos)| Decor::new(*pos, tag.clone()))
+        .collect()
 }
 
 impl Ecosystem {
-    pub fn new() -> Self {
-        let tiles = vec![
-            Rect::new(0, 42, 24, 17),
-            Rect::new(60, 42, 37, 17),
-            Rect::new(0, 13, 1, 30),
-            Rect::new(96, 13, 1, 30),
-            Rect::new(0, 0, 97, 14),
-            Rect::new(35, 42, 12, 4),
-            Rect::new(23, 58, 38, 1),
-        ];
-        let mut bugs = [
-            Wanderer::new(thread_rng().gen_range(0..97), 40, "caterpillar"),
-            Wanderer::new(thread_rng().gen_range(0..97), 40, "caterpillar"),
-        ];
+    pub fn new(arenas: Vec<Arena>, seed: u64, rules: Rules) -> Self {
+        let sim = Simulation::rotating(arenas.clone(), seed, rules);
+        let messages = match &sim.fallback {
+            Some(why) => vec![(4.0, format!("{}, playing a deathmatch", why))],
+            None => Vec::new(),
+        };
         Self {
-            bubbles: Vec::new(),
-            decor: vec![
-                Decor::new((7, 46), "sewage".to_string()),
-                Decor::new((75, 46), "sewage".to_string()),
-                Decor::new((34, 22), "fountain".to_string()),
-                Decor::new((52, 14), "vine".to_string()),
-            ],
-            timer: 1.6,
-            tiles,
-            players: vec![Player::new(14, 22, "p1"), Player::new(97 - 14, 22, "p2")],
-            bugs, // vines: Vec::new(),
-            messages: Vec::new(),
+            decor: decor_of(&sim.arena),
+            decor_arena: sim.arena.name.clone(),
+            replay: Replay::new(arenas, seed, sim.rules.clone()),
+            bots: Vec::new(),
+            sim,
+            messages,
         }
     }
-    pub fn update(&mut self, dt: f32, input: &Input, camera: &mut Vec2) {
-        self.timer += dt;
-        if self.timer > 1.6 {
-            self.timer = 0.0;
-            let iterations: usize = thread_rng().gen_range(4..8);
-            for _i in 0..iterations {
-                self.bubbles
-                    .push(Particle::new(thread_rng().gen_range(24f32..59f32), 57f32));
-            }
+    pub fn rematch(&self) -> Rematch {
+        Rematch {
+            arenas: self.sim.arenas(),
+            seed: self.sim.seed,
+            rules: self.sim.rules.clone(),
+            bots: self.bots.iter().map(|b| (b.player, b.difficulty)).collect(),
+        }
+    }
+    pub fn update(&mut self, dt: f32, input: &Input, controls: &Controls, camera: &mut Vec2) {
+        let keys = read_commands(input, controls);
+        let mut commands = keys[..self.sim.players.len()].to_vec();
+        if !self.bots.is_empty() {
+            //playing alone, any key set moves p1
+            commands[0] = keys.iter().fold(Command::default(), |all, c| all.merge(*c));
+        }
+        for bot in &mut self.bots {
+            commands[bot.player] = bot.think(&self.sim, dt);
+        }
+        self.replay.record(&commands);
+        self.step(dt, &commands, camera);
+    }
+    pub fn step(&mut self, dt: f32, commands: &[Command], camera: &mut Vec2) {
+        self.sim.step(dt, commands);
+        self.react(dt, camera);
+    }
+    //swaps in a state simulated elsewhere, e.g. a netplay prediction
+    pub fn show(&mut self, sim: Simulation, dt: f32, camera: &mut Vec2) {
+        self.sim = sim;
+        self.react(dt, camera);
+    }
+    //animates the decor and shakes the camera for whatever the last simulated frame produced
+    fn react(&mut self, dt: f32, camera: &mut Vec2) {
+        if self.decor_arena != self.sim.arena.name {
+            self.decor = decor_of(&self.sim.arena);
+            self.decor_arena = self.sim.arena.name.clone();
         }
         for decor in &mut self.decor {
             decor.animator.animate(dt);
         }
-        self.bubbles.retain_mut(|bubble| {
-            bubble.y -= dt * bubble.acceleration.y;
-            bubble.x += dt * thread_rng().gen_range(-bubble.acceleration.x..bubble.acceleration.x);
-
-            bubble.alpha -= dt * 120.0;
-            bubble.alpha > 0.0
-        });
-        let bullets0 = self.players[0].gun.bullets.clone();
-        for b0 in bullets0 {
-            if self.players[1]
-                .entity
-                .rect
-                .contains_point(point!(b0.x, b0.y))
-            {
-                camera.x += thread_rng().gen_range(-4.0..4.0);
-                camera.y += thread_rng().gen_range(-4.0..4.0);
-
-                self.players[1].entity.rect.x += (10.0
-                    * self.players[1].entity.physics.acceleration.x
-                    * self.players[0].entity.physics.dir
-                    * -1.0) as i32;
-                self.players[1].entity.hp.0 -= 1.0;
-            }
+        for message in &mut self.messages {
+            message.0 -= dt;
         }
-        let bullets1 = self.players[1].gun.bullets.clone();
-        for b1 in bullets1 {
-            if self.players[0]
-                .entity
-                .rect
-                .contains_point(point!(b1.x, b1.y))
-            {
-                camera.x += thread_rng().gen_range(-4.0..4.0);
-                camera.y += thread_rng().gen_range(-4.0..4.0);
-                self.players[0].entity.rect.x += (10f32
-                    * self.players[0].entity.physics.acceleration.x
-                    * self.players[1].entity.physics.dir
-                    * -1.0) as i32;
-                {
-                    self.players[0].entity.hp.0 -= 1.0;
-                }
+        self.messages.retain(|m| m.0 > 0.0);
+        for event in &self.sim.events {
+            if let Event::Hit { shake, .. } = event {
+                camera.x += shake.0;
+                camera.y += shake.1;
             }
         }
-        println!("{:?}", self.players[0].is_dead());
-        self.players[0].update(
-            dt,
-            [
-                input.is_key_pressed(Keycode::D),
-                input.is_key_pressed(Keycode::A),
-                input.is_key_clicked(Keycode::W),
-                input.is_key_pressed(Keycode::S),
-            ],
-            &self.tiles,
-        );
-        self.players[1].update(
-            dt,
-            [
-                input.is_key_pressed(Keycode::Right),
-                input.is_key_pressed(Keycode::Left),
-                input.is_key_clicked(Keycode::Up),
-                input.is_key_pressed(Keycode::Down),
-            ],
-            &self.tiles,
-        );
-
-        for bug in &mut self.bugs {
-            bug.update(dt, &self.tiles);
+    }
+    pub fn follow(&self, camera: &mut Vec2, win_dim: (i32, i32), dt: f32) {
+        //half the spread of everyone still alive
+        let mut min = Vec2::new(f32::MAX, f32::MAX);
+        let mut max = Vec2::new(f32::MIN, f32::MIN);
+        for player in self.sim.players.iter().filter(|p| !p.is_dead()) {
+            min = Vec2::new(min.x.min(player.pos().x), min.y.min(player.pos().y));
+            max = Vec2::new(max.x.max(player.pos().x), max.y.max(player.pos().y));
+        }
+        if min.x > max.x {
+            return;
         }
+        camera.x += ((((max.x - min.x) / 2.0) - camera.x) - (win_dim.0 as f32 / 2.0)
+            + self.sim.arena.bounds.w as f32 / 4.0)
+            * dt;
+        camera.y += ((((max.y - min.y) / 2.0) - camera.y) - (win_dim.1 as f32 / 2.0)
+            + self.sim.arena.bounds.h as f32 / 4.0)
+            * dt;
     }
     pub fn render<'a>(
         &self,
         display: &mut WindowCanvas,
         cache: &mut HashMap<String, Texture<'a>>,
+        texts: &mut TextCache,
         camera: Vec2,
     ) {
         let x_offset = camera.x as i32;
         let y_offset = camera.y as i32;
         display.set_blend_mode(BlendMode::Blend);
 
-        for bubble in &self.bubbles {
+        for bubble in &self.sim.bubbles {
             display.set_draw_color(Color::RGBA(146, 146, 156, bubble.alpha as u8));
             display.draw_point(point!(bubble.x - camera.x, bubble.y - camera.y));
         }
-        display.copy(
-            cache.get_mut("fish_skeleton").unwrap(),
-            None,
-            Rect::new(24 - x_offset, 55 - y_offset, 6, 3),
-        );
-        display.copy(
-            cache.get_mut("sea_grass1").unwrap(),
-            None,
-            Rect::new(37 - x_offset, 54 - y_offset, 6, 4),
-        );
-        display.copy(
-            cache.get_mut("sea_grass2").unwrap(),
-            None,
-            Rect::new(53 - x_offset, 54 - y_offset, 6, 4),
-        );
+        self.render_sprites(display, cache, (x_offset, y_offset), Layer::Back);
         display.set_draw_color(Color::RGBA(146, 146, 156, 79));
-        display.fill_rect(Rect::new(24 - x_offset, 42 - y_offset, 36, 16));
+        for water in &self.sim.arena.water {
+            display.fill_rect(rect!(water.x - x_offset, water.y - y_offset, water.w, water.h));
+        }
         display.set_blend_mode(BlendMode::None);
 
         for decor in &self.decor {
             decor.render(display, cache, (x_offset, y_offset));
         }
-        display
-            .copy(
-                cache.get_mut("pillar").unwrap(),
-                None,
-                Rect::new(88 - x_offset, 21 - y_offset, 5, 20),
-            )
-            .unwrap();
+        self.render_sprites(display, cache, (x_offset, y_offset), Layer::Middle);
 
-        for bug in &self.bugs {
-            bug.render(display, cache, (x_offset, y_offset));
+        for bug in &self.sim.bugs {
+            draw::creature(display, cache, bug, (x_offset, y_offset));
+        }
+        //waiting pickups bob up and down a pixel
+        let bob = sine_wave(self.sim.frame as f32 / 60.0, 1.5, 1.0, 1.0).round() as i32;
+        for spawner in &self.sim.pickups {
+            let item = match &spawner.item {
+                Some(item) => item,
+                None => continue,
+            };
+            let rect = spawner.rect();
+            let rect = rect!(rect.x - x_offset, rect.y - y_offset + bob, rect.w, rect.h);
+            draw::texture(display, cache, &item.sprite(), None, rect);
         }
 
-        for player in &self.players {
-            player.render(display, cache, (x_offset, y_offset));
+        draw::objective(display, cache, self.sim.objective.view(), (x_offset, y_offset));
+        for player in self.sim.players.iter().filter(|p| !p.is_dead()) {
+            draw::player(display, cache, texts, player, (x_offset, y_offset));
         }
-        display
-            .copy(
-                cache.get_mut("chain").unwrap(),
-                None,
-                Rect::new(18 - x_offset, 14 - y_offset, 3, 13),
-            )
-            .unwrap();
-        display
-            .copy(
-                cache.get_mut("chain").unwrap(),
-                None,
-                Rect::new(79 - x_offset, 14 - y_offset, 3, 13),
-            )
-            .unwrap();
+        self.render_sprites(display, cache, (x_offset, y_offset), Layer::Front);
         display.set_draw_color(Color::RGB(168, 181, 174));
 
-        for tile in &self.tiles {
+        for tile in &self.sim.arena.tiles {
             display.draw_rect(rect!(tile.x - x_offset, tile.y - y_offset, tile.w, tile.h));
         }
-        display.copy(
-            cache.get_mut("grass1").unwrap(),
-            None,
-            Rect::new(11 - x_offset, 13 - y_offset, 4, 4),
-        );
-        display.copy(
-            cache.get_mut("grass2").unwrap(),
-            None,
-            Rect::new(37 - x_offset, 12 - y_offset, 4, 4),
-        );
-        display.copy(
-            cache.get_mut("grass3").unwrap(),
-            None,
-            Rect::new(0 - x_offset, 26 - y_offset, 4, 4),
-        );
-        display.copy(
-            cache.get_mut("grass4").unwrap(),
-            None,
-            Rect::new(93 - x_offset, 40 - y_offset, 4, 4),
-        );
-        display.copy(
-            cache.get_mut("grass5").unwrap(),
-            None,
-            Rect::new(78 - x_offset, 40 - y_offset, 4, 4),
-        );
-        display.copy(
-            cache.get_mut("grass6").unwrap(),
-            None,
-            Rect::new(6 - x_offset, 40 - y_offset, 4, 4),
-        );
+        for (tag, pos) in &self.sim.arena.grass {
+            draw::texture(display, cache, tag, None, Rect::new(pos.0 - x_offset, pos.1 - y_offset, 4, 4));
+        }
+    }
+    //round and clock along the top, the scoreboard between rounds. `dim` is the world view size
+    pub fn render_hud(&self, display: &mut WindowCanvas, texts: &mut TextCache, dim: (i32, i32)) {
+        let sim = &self.sim;
+        let light = Color::RGB(241, 234, 182);
+        if sim.rules.rounds > 1 {
+            text(display, texts, &format!("round {}", sim.round), light, dim.0 / 6, 4, 0.25);
+        }
+        if sim.sudden_death() && sim.intermission <= 0.0 {
+            let red = Color::RGB(212, 139, 139);
+            text(display, texts, "sudden death", red, dim.0 * 5 / 6, 4, 0.25);
+        } else if sim.rules.round_time > 0.0 {
+            let left = (sim.rules.round_time - sim.clock).max(0.0).ceil();
+            text(display, texts, &format!("{}", left), light, dim.0 * 5 / 6, 4, 0.25);
+        }
+        draw::objective_hud(sim, display, texts, dim);
+        for (i, (_, message)) in self.messages.iter().enumerate() {
+            text(display, texts, message, light, dim.0 / 2, dim.1 - 6 - 6 * i as i32, 0.25);
+        }
+        if sim.intermission <= 0.0 {
+            return;
+        }
+        let standings = sim.standings();
+        let top = dim.1 / 3;
+        display.set_blend_mode(BlendMode::Blend);
+        display.set_draw_color(Color::RGBA(0, 0, 0, 160));
+        display
+            .fill_rect(rect!(dim.0 / 4, top - 6, dim.0 / 2, 12 + 6 * standings.len()))
+            .unwrap();
+        display.set_blend_mode(BlendMode::None);
+        let title = match sim.round_winner {
+            Some(team) => format!("{} takes round {}", sim.side_name(team), sim.round),
+            None => format!("nobody takes round {}", sim.round),
+        };
+        text(display, texts, &title, light, dim.0 / 2, top, 0.25);
+        for (i, (side, rounds)) in standings.iter().enumerate() {
+            let line = format!("{}  {}/{}", side, rounds, sim.rules.rounds);
+            let color = Color::RGB(168, 181, 174);
+            text(display, texts, &line, color, dim.0 / 2, top + 8 + 6 * i as i32, 0.25);
+        }
+    }
+    fn render_sprites<'a>(
+        &self,
+        display: &mut WindowCanvas,
+        cache: &mut HashMap<String, Texture<'a>>,
+        offset: (i32, i32),
+        layer: Layer,
+    ) {
+        for sprite in self.sim.arena.sprites.iter().filter(|s| s.layer == layer) {
+            let rect = sprite.rect;
+            let rect = rect!(rect.x - offset.0, rect.y - offset.1, rect.w, rect.h);
+            draw::texture(display, cache, &sprite.tag, None, rect);
+        }
+    }
+}
+
+//every player's keys merged with the pad of the same number, if one is plugged in
+pub fn read_commands(input: &Input, controls: &Controls) -> [Command; MAX_PLAYERS] {
+    std::array::from_fn(|player| {
+        controls
+            .command(input, player)
+            .merge(read_pad(input, player))
+    })
+}
+
+//left stick or d-pad to move, A to jump, X, right bumper or right trigger to shoot
+fn read_pad(input: &Input, pad: usize) -> Command {
+    let stick = input.pad_axis(pad, Axis::LeftX);
+    Command {
+        right: stick > 0.0 || input.is_pad_pressed(pad, Button::DPadRight),
+        left: stick < 0.0 || input.is_pad_pressed(pad, Button::DPadLeft),
+        jump: input.is_pad_clicked(pad, Button::A),
+        shoot: input.is_pad_pressed(pad, Button::X)
+            || input.is_pad_pressed(pad, Button::RightShoulder)
+            || input.pad_axis(pad, Axis::TriggerRight) > 0.5,
     }
 }
@@ -1,31 +1,97 @@
-use crate::components::{Animator, Particle, Physics, Stat};
+use crate::arena::Arena;
+use crate::components::{Animator, Physics, Stat};
+use crate::creatures::{Archetype, Behaviour};
 use crate::math::Vec2;
+use crate::player::Player;
+use crate::weapons::Weapon;
 use crate::{rect, vec2};
 use rand::prelude::*;
-use sdl2::{
-    rect::Rect,
-    render::{Texture, WindowCanvas},
-};
-use std::collections::HashMap;
+use sdl2::rect::{Point, Rect};
 
-pub struct Gun {
+#[derive(Clone)]
+pub struct Bullet {
+    pub x: f32,
+    pub y: f32,
+    //where it was before the last move, hits are checked along the whole way
+    pub last: Vec2,
+    pub vel: Vec2,
+    //how far it has flown, it is gone past the weapon's range
+    pub travelled: f32,
+    pub range: f32,
     pub damage: f32,
-    pub bullets: Vec<Particle>,
+    pub knockback: f32,
+}
+impl Bullet {
+    //moves along this frame's path in half pixel steps so even the fastest bullet
+    //can't skip over a 1 pixel wall. returns the last free spot when it runs into a tile
+    pub fn advance(&mut self, dt: f32, tiles: &[Rect]) -> Option<Vec2> {
+        let (dx, dy) = (self.vel.x * dt, self.vel.y * dt);
+        let steps = (dx.abs().max(dy.abs()) * 2.0).ceil().max(1.0);
+        self.travelled += self.vel.length() * dt;
+        self.last = vec2!(self.x, self.y);
+        for _ in 0..steps as i32 {
+            let (x, y) = (self.x + dx / steps, self.y + dy / steps);
+            let spot = Point::new(x.floor() as i32, y.floor() as i32);
+            if tiles.iter().any(|tile| tile.contains_point(spot)) {
+                return Some(vec2!(self.x, self.y));
+            }
+            self.x = x;
+            self.y = y;
+        }
+        None
+    }
+    //the path since the last move crosses `rect`, the spot it was fired from counts too
+    pub fn crossed(&self, rect: Rect) -> bool {
+        let from = Point::new(self.last.x.floor() as i32, self.last.y.floor() as i32);
+        let to = Point::new(self.x.floor() as i32, self.y.floor() as i32);
+        rect.contains_point(from) || rect.intersect_line(from, to).is_some()
+    }
+}
 
-    pub per_bullet_time: f32,
+#[derive(Clone)]
+pub struct Gun {
+    pub weapon: Weapon,
+    pub bullets: Vec<Bullet>,
     pub cool_down_timer: f32,
 }
 impl Gun {
-    pub fn new(damage: f32, per_bullet_time: f32) -> Self {
+    pub fn new(weapon: Weapon) -> Self {
         Self {
-            damage,
+            weapon,
             bullets: Vec::new(),
-            per_bullet_time,
             cool_down_timer: 0.0,
         }
     }
+    //one shot, every pellet spread at random and flying at its own speed.
+    //`boost` multiplies the damage, e.g. for the double damage power-up
+    pub fn fire(&mut self, x: f32, y: f32, dir: f32, boost: f32, rng: &mut StdRng) {
+        let weapon = &self.weapon;
+        for _ in 0..weapon.pellets {
+            let speed = if weapon.speed.0 < weapon.speed.1 {
+                rng.gen_range(weapon.speed.0..weapon.speed.1)
+            } else {
+                weapon.speed.0
+            };
+            let angle = if weapon.spread > 0.0 {
+                rng.gen_range(-weapon.spread..weapon.spread).to_radians()
+            } else {
+                0.0
+            };
+            self.bullets.push(Bullet {
+                x,
+                y,
+                last: vec2!(x, y),
+                vel: vec2!(speed * angle.cos() * dir, speed * angle.sin()),
+                travelled: 0.0,
+                range: weapon.range,
+                damage: weapon.damage * boost,
+                knockback: weapon.knockback,
+            });
+        }
+    }
 }
 
+#[derive(Clone)]
 pub struct Entity {
     pub physics: Physics,
     pub rect: Rect,
@@ -44,49 +110,125 @@ impl Entity {
     }
 }
 
+//seconds a hunting creature climbs after running into something
+const CLIMB_TIME: f32 = 0.3;
+//seconds a hopper pushes off the ground for
+const HOP_TIME: f32 = 0.15;
+//how close a player gets, either way, before a fleeing creature runs
+const FLEE_RANGE: f32 = 16.0;
+
+#[derive(Clone)]
 pub struct Wanderer {
-    entity: Entity,
-    tag: String,
+    pub entity: Entity,
+    //what it is, from res/creatures.cfg
+    pub kind: &'static Archetype,
+    //goes after players, drawn tinted red
+    pub hostile: bool,
+    //seconds of climbing left
+    lift: f32,
+    //how fast swimmers and fliers go up or down
+    drift: f32,
+    //seconds until a hopper jumps again or a drifter changes course
+    timer: f32,
 }
 impl Wanderer {
-    pub fn new(x: i32, y: i32, tag: &str) -> Self {
-        let mut accel: f32 = 0.0;
-        let mut per_frame_time = 0.0;
-        let mut rect = Rect::new(x, y, 1, 1);
-        if tag == "caterpillar" {
-            accel = thread_rng().gen_range(2.0..4.0);
-            per_frame_time = 0.2;
-            rect.w = 3;
-            rect.h = 2;
-        }
-        if tag == "caterpillar" {
-            accel = thread_rng().gen_range(2.0..4.0);
-            per_frame_time = 0.2;
-            rect.w = 3;
-            rect.h = 2;
-        }
+    pub fn new(x: i32, y: i32, kind: &'static Archetype, rng: &mut StdRng) -> Self {
+        let (w, h) = kind.size;
+        let speed = if kind.speed.0 < kind.speed.1 {
+            rng.gen_range(kind.speed.0..kind.speed.1)
+        } else {
+            kind.speed.0
+        };
         Self {
             entity: Entity::new(
-                rect,
-                Vec2::new(accel, (rect.w + rect.h) as f32 * 9.8), //vy = mass * grav
-                2.0,
+                rect!(x, y, w, h),
+                Vec2::new(speed, (w + h) as f32 * 9.8), //vy = mass * grav
+                kind.hp,
                 Animator::new(
-                    rect!(
-                        *[0, rect.w, rect.w * 2, rect.w * 3]
-                            .choose(&mut thread_rng())
-                            .unwrap(),
-                        0,
-                        rect.w,
-                        rect.h
-                    ),
-                    3,
-                    per_frame_time,
+                    rect!(rng.gen_range(0..kind.frames) * w as i32, 0, w, h),
+                    kind.frames,
+                    kind.frame_time,
                 ),
             ),
-            tag: tag.to_string(),
+            kind,
+            hostile: false,
+            lift: 0.0,
+            drift: 0.0,
+            timer: 0.0,
+        }
+    }
+    //one frame of going about its business the way its kind does
+    pub fn update(&mut self, dt: f32, arena: &Arena, players: &[Player], rng: &mut StdRng) {
+        self.timer -= dt;
+        match self.kind.behaviour {
+            Behaviour::Patrol => {}
+            Behaviour::Flee => {
+                let pos = self.entity.physics.pos;
+                let threat = players
+                    .iter()
+                    .filter(|p| !p.is_dead())
+                    .map(|p| (p.pos().x - pos.x, p.pos().y - pos.y))
+                    .filter(|(dx, dy)| dx.abs() < FLEE_RANGE && dy.abs() < FLEE_RANGE)
+                    .min_by(|a, b| a.0.abs().total_cmp(&b.0.abs()));
+                if let Some((dx, _)) = threat {
+                    if dx != 0.0 {
+                        self.entity.physics.dir = -dx.signum();
+                    }
+                }
+            }
+            Behaviour::Hop => {
+                if self.timer <= 0.0 && self.grounded(&arena.tiles) {
+                    self.lift = HOP_TIME;
+                    self.timer = rng.gen_range(1.0..3.0);
+                }
+            }
+            Behaviour::Swim | Behaviour::Fly => {
+                if self.timer <= 0.0 {
+                    self.drift = rng.gen_range(-0.5..0.5) * self.entity.physics.acceleration.x;
+                    self.timer = rng.gen_range(0.5..2.0);
+                }
+            }
+        }
+        self.step(dt, arena);
+    }
+    //walks towards `target` instead of back and forth, climbing whatever is in the way
+    pub fn hunt(&mut self, dt: f32, arena: &Arena, target: Vec2) {
+        let dx = target.x - self.entity.physics.pos.x;
+        let dy = target.y - self.entity.physics.pos.y;
+        //with the target on another level, keep going past it to find a way there
+        let margin = if dy.abs() > 4.0 { 8.0 } else { 1.0 };
+        //swims up towards a target above, bobbing at the surface
+        if dy < -4.0 && arena.in_water(self.entity.rect) {
+            self.lift = self.lift.max(dt * 2.0);
+        }
+        if dx.abs() > margin {
+            self.entity.physics.dir = dx.signum();
         }
+        let dir = self.entity.physics.dir;
+        self.step(dt, arena);
+        //`step` turns around at walls, climb over them unless the target is down below
+        if self.entity.physics.dir != dir && dy < 4.0 {
+            self.entity.physics.dir = dir;
+            self.lift = CLIMB_TIME;
+        }
+    }
+    fn grounded(&self, tiles: &[Rect]) -> bool {
+        let rect = self.entity.rect;
+        let below = Rect::new(rect.x, rect.y + 1, rect.width(), rect.height());
+        tiles.iter().any(|t| below.has_intersection(*t))
+    }
+    //swimmers stay in their water, everything stays inside the arena
+    fn allowed(&self, arena: &Arena) -> bool {
+        let rect = self.entity.rect;
+        arena.bounds.contains_rect(rect)
+            && (self.kind.behaviour != Behaviour::Swim
+                || arena.water.iter().any(|w| w.contains_rect(rect)))
     }
-    pub fn update(&mut self, dt: f32, tiles: &Vec<Rect>) {
+    //moves along `dir` and turns around at walls, only swimmers and fliers don't fall
+    fn step(&mut self, dt: f32, arena: &Arena) {
+        let tiles = &arena.tiles;
+        let floats = matches!(self.kind.behaviour, Behaviour::Swim | Behaviour::Fly);
+        let before = self.entity.physics.pos;
         self.entity.physics.vel.x = self.entity.physics.dir * self.entity.physics.acceleration.x;
         self.entity.physics.pos.x += self.entity.physics.vel.x * dt;
         self.entity.rect.x = self.entity.physics.pos.x as i32;
@@ -106,7 +248,19 @@ impl Wanderer {
                 }
             }
         }
-        self.entity.physics.vel.y = self.entity.physics.acceleration.y;
+        if !self.allowed(arena) {
+            self.entity.physics.pos.x = before.x;
+            self.entity.rect.x = before.x as i32;
+            self.entity.physics.dir = -self.entity.physics.vel.x.signum();
+        }
+        self.entity.physics.vel.y = if self.lift > 0.0 {
+            -self.entity.physics.acceleration.y
+        } else if floats {
+            self.drift
+        } else {
+            self.entity.physics.acceleration.y
+        };
+        self.lift = (self.lift - dt).max(0.0);
 
         self.entity.physics.pos.y += self.entity.physics.vel.y * dt;
         self.entity.rect.y = self.entity.physics.pos.y as i32;
@@ -120,32 +274,56 @@ impl Wanderer {
                     self.entity.rect.y = tile.y + tile.h;
                     self.entity.physics.pos.y = (tile.y + tile.h) as f32;
                 }
+                self.drift = -self.drift;
             }
         }
+        if !self.allowed(arena) {
+            self.entity.physics.pos.y = before.y;
+            self.entity.rect.y = before.y as i32;
+            self.drift = -self.drift;
+        }
         self.entity.animator.animate(dt);
     }
+}
 
-    pub fn render<'a>(
-        &self,
-        display: &mut WindowCanvas,
-        cache: &mut HashMap<String, Texture<'a>>,
-        offset: (i32, i32),
-    ) {
-        display
-            .copy_ex(
-                cache.get_mut(&self.tag).unwrap(),
-                self.entity.animator.frame,
-                rect!(
-                    self.entity.rect.x - offset.0,
-                    self.entity.rect.y - offset.1,
-                    self.entity.rect.w,
-                    self.entity.rect.h
-                ),
-                0.0,
-                None,
-                self.entity.physics.dir < 0.0,
-                false,
-            )
-            .unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bullet(x: f32, vel: f32) -> Bullet {
+        Bullet {
+            x,
+            y: 5.5,
+            last: vec2!(x, 5.5),
+            vel: vec2!(vel, 0.0),
+            travelled: 0.0,
+            range: 1000.0,
+            damage: 1.0,
+            knockback: 0.0,
+        }
+    }
+
+    #[test]
+    fn fast_bullets_stop_at_thin_walls() {
+        let wall = [Rect::new(20, 0, 1, 10)];
+        //120 pixels in one frame, far past the wall
+        let mut fast = bullet(2.0, 7200.0);
+        let spot = fast.advance(1.0 / 60.0, &wall).unwrap();
+        assert!(spot.x < 20.0 && spot.x >= 19.0, "{:?}", spot);
+        assert!(fast.x < 20.0);
+
+        let mut free = bullet(2.0, 7200.0);
+        assert!(free.advance(1.0 / 60.0, &[]).is_none());
+        assert!((free.x - 122.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn the_whole_path_counts_as_crossed() {
+        let mut fast = bullet(2.0, 7200.0);
+        fast.advance(1.0 / 60.0, &[]);
+        //a 2 pixel wide target the bullet jumped over
+        assert!(fast.crossed(Rect::new(60, 4, 2, 4)));
+        assert!(!fast.crossed(Rect::new(60, 7, 2, 4)));
+        assert!(!fast.crossed(Rect::new(130, 4, 2, 4)));
     }
 }
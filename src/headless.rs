@@ -0,0 +1,136 @@
+use crate::ai::{Bot, Difficulty};
+use crate::arena::Arena;
+use crate::args::Args;
+use crate::netplay::Lobby;
+use crate::replay::Replay;
+use crate::simulation::{Command, Simulation};
+
+//runs one match without a window, every player driven by a bot
+pub fn run(args: &Args, arenas: Vec<Arena>) {
+    let dt = 1.0 / 60.0;
+    let mut sim = Simulation::rotating(arenas, args.seed(), args.rules());
+    let mut replay = Replay::new(sim.arenas(), sim.seed, sim.rules.clone());
+    let difficulty = args.bot.unwrap_or(Difficulty::Normal);
+    let mut bots: Vec<Bot> = (0..sim.players.len())
+        .map(|i| Bot::new(i, difficulty, sim.seed))
+        .collect();
+    println!(
+        "headless match on {}, seed {}, {} {} bots",
+        sim.arena.name,
+        sim.seed,
+        sim.players.len(),
+        difficulty.name()
+    );
+    if let Some(why) = &sim.fallback {
+        println!("{}, playing a deathmatch instead", why);
+    }
+    while !sim.is_over() && sim.frame < args.frames {
+        let commands: Vec<Command> = bots.iter_mut().map(|b| b.think(&sim, dt)).collect();
+        replay.record(&commands);
+        sim.step(dt, &commands);
+    }
+    report(&sim);
+    if args.record {
+        match replay.save("replays") {
+            Ok(path) => println!("replay saved to {}", path.display()),
+            Err(e) => eprintln!("could not save replay: {}", e),
+        }
+    }
+}
+
+//re-simulates a recorded match and prints how it ended
+pub fn replay(path: &str) {
+    let replay = match Replay::load(path) {
+        Ok(replay) => replay,
+        Err(e) => {
+            eprintln!("could not load replay: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut sim = Simulation::rotating(replay.arenas.clone(), replay.seed, replay.rules.clone());
+    println!(
+        "replaying {} frames on {}, seed {}",
+        replay.frames.len(),
+        sim.arena.name,
+        sim.seed
+    );
+    for commands in &replay.frames {
+        sim.step(1.0 / 60.0, commands);
+    }
+    report(&sim);
+}
+
+//plays a bot against another instance over udp, both should print the same result
+pub fn netplay(args: &Args, mut lobby: Lobby) {
+    let dt = 1.0 / 60.0;
+    println!("{}", lobby.status());
+    let mut session = loop {
+        if let Some(session) = lobby.poll() {
+            break session;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    };
+    println!(
+        "connected, playing {} with seed {} as p{}",
+        session.arena().name,
+        session.seed(),
+        session.local + 1
+    );
+    let mut bot = Bot::new(
+        session.local,
+        args.bot.unwrap_or(Difficulty::Normal),
+        session.seed(),
+    );
+    let mut shown = session.confirmed.clone();
+    let mut next = std::time::Instant::now();
+    while !session.confirmed.is_over()
+        && !session.disconnected
+        && session.confirmed.frame < args.frames
+    {
+        if let Some(predicted) = session.tick(dt, bot.think(&shown, dt)) {
+            shown = predicted;
+        }
+        next += std::time::Duration::from_secs_f32(dt);
+        std::thread::sleep(next.saturating_duration_since(std::time::Instant::now()));
+    }
+    let linger = std::time::Instant::now();
+    while linger.elapsed().as_secs_f32() < 1.0 {
+        session.linger();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    session.leave();
+    report(&session.confirmed);
+}
+
+fn report(sim: &Simulation) {
+    for player in &sim.players {
+        println!(
+            "{} (team {}): hp {} at {:?}",
+            player.title(),
+            player.team + 1,
+            player.entity.hp.0,
+            player.pos()
+        );
+    }
+    //survival is not won by anyone, only how far everyone got
+    if let Some(summary) = sim.objective.summary() {
+        let state = if sim.is_over() {
+            "all down"
+        } else {
+            "still going"
+        };
+        println!("{}, {} after {} frames", summary, state, sim.frame);
+        return;
+    }
+    let score: Vec<String> = sim
+        .standings()
+        .iter()
+        .map(|(side, rounds)| format!("{} {}", side, rounds))
+        .collect();
+    println!("rounds won: {}", score.join(", "));
+    if sim.is_over() {
+        println!("{} won after {} frames", sim.winner(), sim.frame);
+    } else {
+        println!("no winner after {} frames", sim.frame);
+    }
+}
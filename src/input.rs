@@ -1,6 +1,8 @@
 use crate::math::Vec2;
+use crate::simulation::MAX_PLAYERS;
 use crate::vec2;
-use sdl2::EventPump;
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::{EventPump, GameControllerSubsystem};
 
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
@@ -8,25 +10,63 @@ use sdl2::keyboard::Keycode;
 use sdl2::mouse::MouseButton;
 
 use std::collections::HashSet;
+
+//sticks never rest exactly at zero, anything inside this fraction of the full range is centred
+const DEADZONE: f32 = 0.25;
+const BUTTONS: [Button; 15] = [
+    Button::A,
+    Button::B,
+    Button::X,
+    Button::Y,
+    Button::Back,
+    Button::Guide,
+    Button::Start,
+    Button::LeftStick,
+    Button::RightStick,
+    Button::LeftShoulder,
+    Button::RightShoulder,
+    Button::DPadUp,
+    Button::DPadDown,
+    Button::DPadLeft,
+    Button::DPadRight,
+];
+
+//one connected game controller. a pad takes the first free slot when it is plugged in and keeps
+//it, so unplugging one doesn't hand the others to different players
+pub struct Pad {
+    controller: GameController,
+    prev_buttons: HashSet<Button>,
+    new_buttons: HashSet<Button>,
+}
+
 pub struct Input {
     event_pump: EventPump,
+    controllers: Option<GameControllerSubsystem>,
+    pads: [Option<Pad>; MAX_PLAYERS],
     quit: bool,
+    close_requested: bool,
     prev_keys: HashSet<Keycode>,
     new_keys: HashSet<Keycode>,
     old_keys: HashSet<Keycode>,
+    //held keys in the order they went down, the sets above don't keep one
+    key_order: Vec<Keycode>,
     prev_mouse: HashSet<MouseButton>,
     new_mouse: HashSet<MouseButton>,
     old_mouse: HashSet<MouseButton>,
     mouse_position: Vec2,
 }
 impl Input {
-    pub fn new(event_pump: EventPump) -> Self {
+    pub fn new(event_pump: EventPump, controllers: Option<GameControllerSubsystem>) -> Self {
         Self {
             event_pump,
+            controllers,
+            pads: Default::default(),
             quit: false,
+            close_requested: false,
             prev_keys: HashSet::new(),
             new_keys: HashSet::new(),
             old_keys: HashSet::new(),
+            key_order: Vec::new(),
             prev_mouse: HashSet::new(),
             new_mouse: HashSet::new(),
             old_mouse: HashSet::new(),
@@ -36,12 +76,28 @@ impl Input {
     pub fn quit(&self) -> bool {
         self.quit
     }
+    //ends the main loop, only after the player confirmed it
+    pub fn exit(&mut self) {
+        self.quit = true;
+    }
+    //the window was closed this frame
+    pub fn close_requested(&self) -> bool {
+        self.close_requested
+    }
     pub fn is_key_pressed(&self, key: Keycode) -> bool {
         self.prev_keys.contains(&key)
     }
     pub fn is_key_clicked(&self, key: Keycode) -> bool {
         self.new_keys.contains(&key)
     }
+    //the first key that went down this frame
+    pub fn clicked_key(&self) -> Option<Keycode> {
+        self.key_order
+            .iter()
+            .find(|k| self.new_keys.contains(k))
+            .or_else(|| self.new_keys.iter().min_by_key(|k| **k as i32))
+            .copied()
+    }
     pub fn is_key_released(&self, key: Keycode) -> bool {
         self.old_keys.contains(&key)
     }
@@ -57,6 +113,30 @@ impl Input {
     pub fn is_mouse_released(&self, btn: MouseButton) -> bool {
         self.old_mouse.contains(&btn)
     }
+    pub fn is_pad_pressed(&self, pad: usize, btn: Button) -> bool {
+        self.pads
+            .get(pad)
+            .and_then(Option::as_ref)
+            .is_some_and(|p| p.prev_buttons.contains(&btn))
+    }
+    pub fn is_pad_clicked(&self, pad: usize, btn: Button) -> bool {
+        self.pads
+            .get(pad)
+            .and_then(Option::as_ref)
+            .is_some_and(|p| p.new_buttons.contains(&btn))
+    }
+    //-1..1 for sticks and 0..1 for triggers, 0 inside the deadzone
+    pub fn pad_axis(&self, pad: usize, axis: Axis) -> f32 {
+        let value = match self.pads.get(pad).and_then(Option::as_ref) {
+            Some(p) => p.controller.axis(axis) as f32 / i16::MAX as f32,
+            None => return 0.0,
+        };
+        if value.abs() < DEADZONE {
+            0.0
+        } else {
+            value.signum() * ((value.abs() - DEADZONE) / (1.0 - DEADZONE)).min(1.0)
+        }
+    }
     pub fn update(&mut self) {
         let keys = self
             .event_pump
@@ -77,19 +157,63 @@ impl Input {
 
         self.prev_mouse = mouse;
         self.prev_keys = keys;
+        self.key_order.retain(|k| self.prev_keys.contains(k));
 
+        self.close_requested = false;
         for event in self.event_pump.poll_iter() {
             match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                } => self.quit = true,
+                Event::Quit { .. } => self.close_requested = true,
                 Event::MouseMotion { x, y, .. } => {
                     self.mouse_position = vec2!(x, y);
                 }
+                //these show up in the key sets on the next update
+                Event::KeyDown {
+                    keycode: Some(key),
+                    repeat: false,
+                    ..
+                } if !self.key_order.contains(&key) => self.key_order.push(key),
+                //also sent once for every pad already plugged in at startup
+                Event::ControllerDeviceAdded { which, .. } => {
+                    let free = self.pads.iter().position(Option::is_none);
+                    if let (Some(controllers), Some(slot)) = (&self.controllers, free) {
+                        match controllers.open(which) {
+                            Ok(controller) => {
+                                println!(
+                                    "controller connected: {} (pad {})",
+                                    controller.name(),
+                                    slot + 1
+                                );
+                                self.pads[slot] = Some(Pad {
+                                    controller,
+                                    prev_buttons: HashSet::new(),
+                                    new_buttons: HashSet::new(),
+                                });
+                            }
+                            Err(e) => eprintln!("could not open controller: {}", e),
+                        }
+                    }
+                }
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    for slot in &mut self.pads {
+                        if slot
+                            .as_ref()
+                            .is_some_and(|p| p.controller.instance_id() == which)
+                        {
+                            let pad = slot.take().unwrap();
+                            println!("controller disconnected: {}", pad.controller.name());
+                        }
+                    }
+                }
                 _ => {}
             }
         }
+        for pad in self.pads.iter_mut().flatten() {
+            let buttons: HashSet<Button> = BUTTONS
+                .into_iter()
+                .filter(|b| pad.controller.button(*b))
+                .collect();
+            pad.new_buttons = &buttons - &pad.prev_buttons;
+            pad.prev_buttons = buttons;
+        }
     }
 }
@@ -23,6 +23,9 @@ impl Vec2 {
     pub fn mult(&mut self, n: f32) -> Vec2 {
         Vec2::new(self.x * n, self.y * n)
     }
+    pub fn length(&self) -> f32 {
+        (self.x * self.x + self.y * self.y).sqrt()
+    }
 }
 impl std::ops::Mul for Vec2 {
     type Output = Self;
@@ -0,0 +1,383 @@
+use crate::arena::Arena;
+use crate::player::Player;
+use crate::simulation::Event;
+use crate::survival::Survival;
+use rand::prelude::*;
+use sdl2::rect::Rect;
+
+//seconds a team has to hold the hill alone to take the round
+pub const HILL_TIME: f32 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Deathmatch,
+    KingOfTheHill,
+    CaptureTheFlag,
+    Survival,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 4] = [
+        Mode::Deathmatch,
+        Mode::KingOfTheHill,
+        Mode::CaptureTheFlag,
+        Mode::Survival,
+    ];
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Deathmatch => "deathmatch",
+            Mode::KingOfTheHill => "koth",
+            Mode::CaptureTheFlag => "ctf",
+            Mode::Survival => "survival",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Mode> {
+        Mode::ALL.into_iter().find(|m| m.name() == name)
+    }
+    //why `arena` can't hold this mode for `sides` sides, if it can't
+    pub fn check(self, arena: &Arena, sides: usize) -> Result<(), String> {
+        match self {
+            Mode::KingOfTheHill if arena.hill.is_none() => {
+                Err(format!("{} has no `hill` for king of the hill", arena.name))
+            }
+            Mode::CaptureTheFlag if arena.flags.len() < sides => Err(format!(
+                "{} has flags for {} sides, the match has {}",
+                arena.name,
+                arena.flags.len(),
+                sides
+            )),
+            _ => Ok(()),
+        }
+    }
+    //the rules of this mode for one match on `arena`, which passed `check`
+    pub fn start(self, arena: &Arena, sides: usize) -> Box<dyn GameMode> {
+        match self {
+            Mode::Deathmatch => Box::new(Deathmatch),
+            Mode::KingOfTheHill => Box::new(KingOfTheHill {
+                hill: arena.hill,
+                held: vec![0.0; sides],
+            }),
+            Mode::CaptureTheFlag => Box::new(CaptureTheFlag {
+                //flags past the last team are left out
+                flags: arena
+                    .flags
+                    .iter()
+                    .take(sides)
+                    .enumerate()
+                    .map(|(team, home)| Flag {
+                        team,
+                        home: *home,
+                        pos: *home,
+                        carrier: None,
+                    })
+                    .collect(),
+                captured: None,
+            }),
+            Mode::Survival => Box::new(Survival::new(arena)),
+        }
+    }
+}
+
+//a mode's state as far as drawing it goes
+pub enum View<'a> {
+    Nothing,
+    Hill(&'a KingOfTheHill),
+    Flags(&'a CaptureTheFlag),
+    Waves(&'a Survival),
+}
+
+//the parts of the simulation a mode may change during a step
+pub struct Field<'a> {
+    pub arena: &'a Arena,
+    pub players: &'a mut [Player],
+    pub events: &'a mut Vec<Event>,
+    pub rng: &'a mut StdRng,
+}
+
+//what a match is played for. lives inside the simulation, so it has to stay deterministic
+pub trait GameMode {
+    //once a step, after everyone moved
+    fn update(&mut self, _field: &mut Field, _dt: f32) {}
+    //Some once the round is over, holding the team that took it
+    fn decide(&self, players: &[Player]) -> Option<Option<usize>> {
+        last_standing(players)
+    }
+    //the match ends whatever the score, e.g. once everyone fell in survival
+    fn finished(&self) -> bool {
+        false
+    }
+    //how it went, for modes that are not won by anyone
+    fn summary(&self) -> Option<String> {
+        None
+    }
+    //the wave reached and creatures killed, for the survival high scores
+    fn progress(&self) -> Option<(usize, usize)> {
+        None
+    }
+    //back to the start for the next round
+    fn reset(&mut self) {}
+    //where a bot should head when it has no clear shot, None to go after the nearest foe
+    fn goal(&self, _player: usize, _players: &[Player]) -> Option<Rect> {
+        None
+    }
+    //something besides rival players for a bot to shoot at
+    fn prey(&self, _player: usize, _players: &[Player]) -> Option<Rect> {
+        None
+    }
+    //what there is to show of it, the drawing itself happens outside the simulation in `draw`
+    fn view(&self) -> View<'_> {
+        View::Nothing
+    }
+    fn clone_box(&self) -> Box<dyn GameMode>;
+}
+
+impl Clone for Box<dyn GameMode> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+//one team left standing, None inside if nobody is
+pub fn last_standing(players: &[Player]) -> Option<Option<usize>> {
+    let mut alive = players.iter().filter(|p| !p.is_dead()).map(|p| p.team);
+    match alive.next() {
+        Some(team) if alive.all(|t| t == team) => Some(Some(team)),
+        Some(_) => None,
+        None => Some(None),
+    }
+}
+
+//last team standing takes the round
+#[derive(Clone)]
+pub struct Deathmatch;
+impl GameMode for Deathmatch {
+    fn clone_box(&self) -> Box<dyn GameMode> {
+        Box::new(self.clone())
+    }
+}
+
+//the first team to hold the hill alone long enough, or the last one standing
+#[derive(Clone)]
+pub struct KingOfTheHill {
+    //only None on arenas without a `hill` line, which get a deathmatch instead
+    pub hill: Option<Rect>,
+    //seconds each team held it this round
+    pub held: Vec<f32>,
+}
+impl KingOfTheHill {
+    //the only team with anyone alive on the hill
+    fn holder(&self, players: &[Player]) -> Option<usize> {
+        let hill = self.hill?;
+        let mut on_hill = players
+            .iter()
+            .filter(|p| !p.is_dead() && p.entity.rect.has_intersection(hill))
+            .map(|p| p.team);
+        let team = on_hill.next()?;
+        on_hill.all(|t| t == team).then_some(team)
+    }
+}
+impl GameMode for KingOfTheHill {
+    fn update(&mut self, field: &mut Field, dt: f32) {
+        if let Some(team) = self.holder(field.players) {
+            self.held[team] += dt;
+        }
+    }
+    fn decide(&self, players: &[Player]) -> Option<Option<usize>> {
+        match self.held.iter().position(|held| *held >= HILL_TIME) {
+            Some(team) => Some(Some(team)),
+            None => last_standing(players),
+        }
+    }
+    fn reset(&mut self) {
+        self.held.iter_mut().for_each(|held| *held = 0.0);
+    }
+    fn goal(&self, _player: usize, _players: &[Player]) -> Option<Rect> {
+        self.hill
+    }
+    fn view(&self) -> View<'_> {
+        View::Hill(self)
+    }
+    fn clone_box(&self) -> Box<dyn GameMode> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Clone)]
+pub struct Flag {
+    pub team: usize,
+    pub home: (i32, i32),
+    pub pos: (i32, i32),
+    //the player running off with it
+    pub carrier: Option<usize>,
+}
+impl Flag {
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.pos.0, self.pos.1, 3, 5)
+    }
+    pub fn at_home(&self) -> bool {
+        self.carrier.is_none() && self.pos == self.home
+    }
+}
+
+//bring a rival flag to your own while yours is safe at home, or be the last one standing
+#[derive(Clone)]
+pub struct CaptureTheFlag {
+    pub flags: Vec<Flag>,
+    pub captured: Option<usize>,
+}
+impl GameMode for CaptureTheFlag {
+    fn update(&mut self, field: &mut Field, _dt: f32) {
+        let (players, events) = (&*field.players, &mut *field.events);
+        for i in 0..self.flags.len() {
+            let flag = &mut self.flags[i];
+            if let Some(carrier) = flag.carrier {
+                let rect = players[carrier].entity.rect;
+                if players[carrier].is_dead() {
+                    //dropped where its carrier fell
+                    flag.carrier = None;
+                    flag.pos = (rect.x, rect.bottom() - 5);
+                } else {
+                    //held up over their head
+                    flag.pos = (rect.x, rect.y - 5);
+                }
+                continue;
+            }
+            let carrying: Vec<usize> = self.flags.iter().filter_map(|f| f.carrier).collect();
+            let flag = &mut self.flags[i];
+            let toucher = players
+                .iter()
+                .enumerate()
+                .find(|(_, p)| !p.is_dead() && p.entity.rect.has_intersection(flag.rect()));
+            match toucher {
+                //a dropped flag goes straight home when its own team touches it
+                Some((_, player)) if player.team == flag.team && !flag.at_home() => {
+                    flag.pos = flag.home;
+                    events.push(Event::Flag { team: flag.team });
+                }
+                Some((taker, player)) if player.team != flag.team && !carrying.contains(&taker) => {
+                    flag.carrier = Some(taker);
+                    events.push(Event::Flag { team: flag.team });
+                }
+                _ => {}
+            }
+        }
+        for flag in &self.flags {
+            let carrier = match flag.carrier {
+                Some(carrier) => carrier,
+                None => continue,
+            };
+            let team = players[carrier].team;
+            let home = self.flags.iter().find(|f| f.team == team);
+            if home.is_some_and(|f| {
+                f.at_home() && players[carrier].entity.rect.has_intersection(f.rect())
+            }) {
+                self.captured = Some(team);
+            }
+        }
+    }
+    fn decide(&self, players: &[Player]) -> Option<Option<usize>> {
+        match self.captured {
+            Some(team) => Some(Some(team)),
+            None => last_standing(players),
+        }
+    }
+    //run home with a flag, bring back your own, otherwise go take one
+    fn goal(&self, player: usize, players: &[Player]) -> Option<Rect> {
+        let team = players[player].team;
+        let own = self.flags.iter().find(|f| f.team == team);
+        if self.flags.iter().any(|f| f.carrier == Some(player)) {
+            return own.map(|f| Rect::new(f.home.0, f.home.1, 3, 5));
+        }
+        if let Some(own) = own.filter(|f| f.carrier.is_none() && !f.at_home()) {
+            return Some(own.rect());
+        }
+        self.flags
+            .iter()
+            .find(|f| f.team != team && f.carrier.is_none())
+            .map(|f| f.rect())
+    }
+    fn reset(&mut self) {
+        for flag in &mut self.flags {
+            flag.pos = flag.home;
+            flag.carrier = None;
+        }
+        self.captured = None;
+    }
+    fn view(&self) -> View<'_> {
+        View::Flags(self)
+    }
+    fn clone_box(&self) -> Box<dyn GameMode> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARENA: &str =
+        "bounds 0 0 60 30\nspawn 2 2\nspawn 40 2\nhill 20 10 10 10\nflag 5 10\nflag 50 10\n";
+
+    //a p1 on team 0 and a p2 on team 1, standing where asked
+    fn players(p1: (i32, i32), p2: (i32, i32)) -> Vec<Player> {
+        vec![
+            Player::new(p1.0, p1.1, "p1", 0),
+            Player::new(p2.0, p2.1, "p2", 1),
+        ]
+    }
+
+    //runs `seconds` of the mode at 60 frames a second
+    fn play(mode: &mut dyn GameMode, arena: &Arena, players: &mut [Player], seconds: f32) {
+        let mut events = Vec::new();
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..(seconds * 60.0) as usize {
+            let mut field = Field {
+                arena,
+                players,
+                events: &mut events,
+                rng: &mut rng,
+            };
+            mode.update(&mut field, 1.0 / 60.0);
+        }
+    }
+
+    #[test]
+    fn holding_the_hill_long_enough_wins() {
+        let arena = Arena::parse("test.arena", ARENA).unwrap();
+        let mut koth = Mode::KingOfTheHill.start(&arena, 2);
+        let mut players = players((22, 12), (40, 2));
+        play(&mut *koth, &arena, &mut players, HILL_TIME / 2.0);
+        assert_eq!(koth.decide(&players), None);
+        play(&mut *koth, &arena, &mut players, HILL_TIME / 2.0 + 0.1);
+        assert_eq!(koth.decide(&players), Some(Some(0)));
+    }
+
+    #[test]
+    fn a_contested_hill_scores_nothing() {
+        let arena = Arena::parse("test.arena", ARENA).unwrap();
+        let mut koth = Mode::KingOfTheHill.start(&arena, 2);
+        let mut players = players((22, 12), (25, 12));
+        play(&mut *koth, &arena, &mut players, HILL_TIME * 2.0);
+        assert_eq!(koth.decide(&players), None);
+    }
+
+    #[test]
+    fn a_flag_carried_home_scores_and_goes_back() {
+        let arena = Arena::parse("test.arena", ARENA).unwrap();
+        let mut ctf = Mode::CaptureTheFlag.start(&arena, 2);
+        //p1 grabs p2's flag, then runs it back to their own
+        let mut players = players((50, 10), (40, 25));
+        play(&mut *ctf, &arena, &mut players, 0.1);
+        assert_eq!(ctf.decide(&players), None);
+        players[0].entity.rect.set_x(5);
+        play(&mut *ctf, &arena, &mut players, 0.1);
+        assert_eq!(ctf.decide(&players), Some(Some(0)));
+
+        ctf.reset();
+        assert_eq!(ctf.decide(&players), None);
+        match ctf.view() {
+            View::Flags(ctf) => assert!(ctf.flags.iter().all(|f| f.at_home())),
+            _ => panic!("not capture the flag"),
+        }
+    }
+}
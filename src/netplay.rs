@@ -0,0 +1,374 @@
+use crate::arena::Arena;
+use crate::modes::Mode;
+use crate::replay::{check_fingerprints, read_arenas, read_rules, write_arenas, write_fingerprints,
+    write_rules, write_varint, Reader, Replay};
+use crate::simulation::{Command, Rules, Simulation};
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+const VERSION: u8 = 3;
+//frames between pressing a key and it taking effect, hides most lan latency
+pub const INPUT_DELAY: usize = 2;
+//how far ahead of the last confirmed remote input we are allowed to predict
+pub const MAX_ROLLBACK: usize = 8;
+const MAX_SEND: usize = 64;
+const TIMEOUT: Duration = Duration::from_secs(5);
+const HELLO_EVERY: Duration = Duration::from_millis(250);
+
+const HELLO: u8 = b'H';
+const START: u8 = b'S';
+const INPUT: u8 = b'I';
+const QUIT: u8 = b'Q';
+
+//waiting for the other machine before a match can start
+pub enum Lobby {
+    Hosting {
+        socket: UdpSocket,
+        arenas: Vec<Arena>,
+        seed: u64,
+        rules: Rules,
+    },
+    Joining {
+        socket: UdpSocket,
+        host: SocketAddr,
+        last_hello: Option<Instant>,
+    },
+}
+
+impl Lobby {
+    //`rules` beyond the player count carry over, netplay is always one on one
+    pub fn host(port: u16, arenas: Vec<Arena>, seed: u64, rules: Rules) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        socket.set_nonblocking(true)?;
+        let teams = if rules.mode == Mode::Survival { vec![0, 0] } else { vec![0, 1] };
+        Ok(Lobby::Hosting {
+            socket,
+            arenas,
+            seed,
+            rules: Rules { teams, ..rules },
+        })
+    }
+    pub fn join(host: &str) -> std::io::Result<Self> {
+        let host = host.parse().map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("`{}` is not an ip:port address", host),
+            )
+        })?;
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.set_nonblocking(true)?;
+        Ok(Lobby::Joining {
+            socket,
+            host,
+            last_hello: None,
+        })
+    }
+    pub fn status(&self) -> String {
+        match self {
+            Lobby::Hosting { socket, .. } => format!(
+                "waiting on port {}",
+                socket.local_addr().map(|a| a.port()).unwrap_or(0)
+            ),
+            Lobby::Joining { host, .. } => format!("joining {}", host),
+        }
+    }
+    //returns the session once both sides agreed on the arenas, seed and rules
+    pub fn poll(&mut self) -> Option<Session> {
+        let mut buf = [0u8; 65536];
+        match self {
+            Lobby::Hosting {
+                socket,
+                arenas,
+                seed,
+                rules,
+            } => {
+                while let Ok((len, from)) = socket.recv_from(&mut buf) {
+                    if len == 2 && buf[0] == HELLO && buf[1] == VERSION {
+                        let socket = socket.try_clone().ok()?;
+                        let mut session =
+                            Session::new(socket, from, 0, arenas.clone(), *seed, rules.clone());
+                        session.start_packet = start_packet(arenas, *seed, rules);
+                        session.send_start();
+                        return Some(session);
+                    }
+                }
+                None
+            }
+            Lobby::Joining {
+                socket,
+                host,
+                last_hello,
+            } => {
+                if last_hello.is_none_or(|t| t.elapsed() > HELLO_EVERY) {
+                    let _ = socket.send_to(&[HELLO, VERSION], *host);
+                    *last_hello = Some(Instant::now());
+                }
+                while let Ok((len, from)) = socket.recv_from(&mut buf) {
+                    if from != *host || len == 0 || buf[0] != START {
+                        continue;
+                    }
+                    match read_start(&buf[1..len]) {
+                        Ok((arenas, seed, rules)) => {
+                            let socket = socket.try_clone().ok()?;
+                            return Some(Session::new(socket, *host, 1, arenas, seed, rules));
+                        }
+                        Err(e) => eprintln!("bad start packet from host: {}", e),
+                    }
+                }
+                None
+            }
+        }
+    }
+}
+
+//a running one on one match against a remote player.
+//local input is delayed by INPUT_DELAY frames, remote input is predicted by repeating
+//the last one received, and every tick the prediction is rebuilt from the last state
+//both sides agree on, so a late packet just rolls the shown frame back and forward again
+pub struct Session {
+    socket: UdpSocket,
+    peer: SocketAddr,
+    pub local: usize,
+    pub confirmed: Simulation,
+    pub replay: Replay,
+    pub disconnected: bool,
+    local_inputs: Vec<Command>,
+    remote_inputs: Vec<Command>,
+    peer_ack: usize,
+    last_heard: Instant,
+    start_packet: Vec<u8>,
+}
+
+impl Session {
+    fn new(socket: UdpSocket, peer: SocketAddr, local: usize, arenas: Vec<Arena>, seed: u64,
+        rules: Rules) -> Self {
+        let confirmed = Simulation::rotating(arenas, seed, rules);
+        Self {
+            socket,
+            peer,
+            local,
+            replay: Replay::new(confirmed.arenas(), seed, confirmed.rules.clone()),
+            confirmed,
+            disconnected: false,
+            local_inputs: vec![Command::default(); INPUT_DELAY],
+            remote_inputs: vec![Command::default(); INPUT_DELAY],
+            peer_ack: 0,
+            last_heard: Instant::now(),
+            start_packet: Vec::new(),
+        }
+    }
+    pub fn seed(&self) -> u64 {
+        self.confirmed.seed
+    }
+    pub fn arena(&self) -> &Arena {
+        &self.confirmed.arena
+    }
+    //frames shown so far, the local player is always INPUT_DELAY frames ahead of this
+    pub fn frame(&self) -> usize {
+        self.local_inputs.len() - INPUT_DELAY
+    }
+    pub fn stalled(&self) -> bool {
+        self.frame() >= self.remote_inputs.len() + MAX_ROLLBACK
+    }
+    //feeds this frame's local input, returns the predicted state to show,
+    //or None while waiting for the remote player to catch up
+    pub fn tick(&mut self, dt: f32, command: Command) -> Option<Simulation> {
+        self.receive();
+        if self.last_heard.elapsed() > TIMEOUT {
+            self.disconnected = true;
+        }
+        if self.disconnected || self.stalled() {
+            self.send_inputs();
+            return None;
+        }
+        self.local_inputs.push(command);
+        self.send_inputs();
+
+        let confirmed = self.frame().min(self.remote_inputs.len());
+        while (self.confirmed.frame as usize) < confirmed {
+            let commands = self.commands(self.confirmed.frame as usize);
+            self.replay.record(&commands);
+            self.confirmed.step(dt, &commands);
+        }
+        let mut predicted = self.confirmed.clone();
+        while (predicted.frame as usize) < self.frame() {
+            let commands = self.commands(predicted.frame as usize);
+            predicted.step(dt, &commands);
+        }
+        Some(predicted)
+    }
+    //keeps answering the peer after the match ended so it can confirm the last frames too
+    pub fn linger(&mut self) {
+        self.receive();
+        self.send_inputs();
+    }
+    pub fn leave(&mut self) {
+        let _ = self.socket.send_to(&[QUIT], self.peer);
+        self.disconnected = true;
+    }
+    fn commands(&self, frame: usize) -> Vec<Command> {
+        let local = self.local_inputs[frame];
+        let remote = match self.remote_inputs.get(frame) {
+            Some(command) => *command,
+            None => *self.remote_inputs.last().unwrap(),
+        };
+        if self.local == 0 {
+            vec![local, remote]
+        } else {
+            vec![remote, local]
+        }
+    }
+    fn send_start(&self) {
+        let _ = self.socket.send_to(&self.start_packet, self.peer);
+    }
+    //sends every local input the peer has not acknowledged yet, so lost packets heal themselves
+    fn send_inputs(&self) {
+        let start = self.peer_ack.min(self.local_inputs.len());
+        let end = self.local_inputs.len().min(start + MAX_SEND);
+        let mut packet = vec![INPUT];
+        write_varint(&mut packet, self.remote_inputs.len() as u64);
+        write_varint(&mut packet, start as u64);
+        packet.push((end - start) as u8);
+        packet.extend(self.local_inputs[start..end].iter().map(|c| c.bits()));
+        let _ = self.socket.send_to(&packet, self.peer);
+    }
+    fn receive(&mut self) {
+        let mut buf = [0u8; 65536];
+        while let Ok((len, from)) = self.socket.recv_from(&mut buf) {
+            if from != self.peer || len == 0 {
+                continue;
+            }
+            self.last_heard = Instant::now();
+            match buf[0] {
+                HELLO if !self.start_packet.is_empty() => self.send_start(),
+                QUIT => self.disconnected = true,
+                INPUT => {
+                    let mut reader = Reader {
+                        bytes: &buf[1..len],
+                        at: 0,
+                        path: "peer",
+                    };
+                    let packet = (|| {
+                        let ack = reader.varint()? as usize;
+                        let start = reader.varint()? as usize;
+                        let count = reader.take(1)?[0] as usize;
+                        Ok::<_, crate::data::DataError>((ack, start, reader.take(count)?))
+                    })();
+                    if let Ok((ack, start, bits)) = packet {
+                        self.peer_ack = self.peer_ack.max(ack);
+                        for (i, bits) in bits.iter().enumerate() {
+                            if start + i == self.remote_inputs.len() {
+                                self.remote_inputs.push(Command::from_bits(*bits));
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+//the host's data fingerprints go along, a joiner with other weapons or creatures would desync
+fn start_packet(arenas: &[Arena], seed: u64, rules: &Rules) -> Vec<u8> {
+    let mut packet = vec![START, VERSION];
+    packet.extend_from_slice(&seed.to_le_bytes());
+    write_arenas(&mut packet, arenas);
+    write_rules(&mut packet, rules);
+    write_fingerprints(&mut packet);
+    packet
+}
+
+fn read_start(bytes: &[u8]) -> Result<(Vec<Arena>, u64, Rules), crate::data::DataError> {
+    let mut reader = Reader {
+        bytes,
+        at: 0,
+        path: "host",
+    };
+    if reader.take(1)?[0] != VERSION {
+        return Err(reader.error("host runs a different game version"));
+    }
+    let seed = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+    let arenas = read_arenas(&mut reader)?;
+    let rules = read_rules(&mut reader)?;
+    if rules.players() != 2 {
+        return Err(reader.error("netplay is for two players"));
+    }
+    check_fingerprints(&mut reader, "the match would desync")?;
+    Ok((arenas, seed, rules))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //each side changes its input every few frames, so predictions keep getting rolled back
+    fn scripted(frame: usize, side: usize) -> Command {
+        let t = frame + side * 13;
+        Command {
+            right: t % 90 < 45,
+            left: t % 90 >= 45,
+            jump: t.is_multiple_of(20),
+            shoot: t.is_multiple_of(7),
+        }
+    }
+
+    #[test]
+    fn two_lobbies_on_localhost_play_the_same_match() {
+        let arena = Arena::load("res/arenas/jungle.arena").unwrap();
+        let rules = Rules {
+            friendly_fire: true,
+            rounds: 9,
+            ..Rules::free_for_all(4)
+        };
+        let mut hosting = Lobby::host(0, vec![arena], 11, rules).unwrap();
+        let port = match &hosting {
+            Lobby::Hosting { socket, .. } => socket.local_addr().unwrap().port(),
+            Lobby::Joining { .. } => unreachable!(),
+        };
+        let mut joining = Lobby::join(&format!("127.0.0.1:{}", port)).unwrap();
+        let (mut host, mut guest) = (None, None);
+        let started = Instant::now();
+        while host.is_none() || guest.is_none() {
+            assert!(started.elapsed() < TIMEOUT, "lobbies never met");
+            host = host.or_else(|| hosting.poll());
+            guest = guest.or_else(|| joining.poll());
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let mut sessions = [host.unwrap(), guest.unwrap()];
+        //the guest plays by the host's rules, cut down to two players
+        assert_eq!(sessions[1].confirmed.rules, sessions[0].confirmed.rules);
+        assert_eq!(sessions[1].confirmed.rules.players(), 2);
+        assert!(sessions[1].confirmed.rules.friendly_fire);
+
+        let frames = 300;
+        let dt = 1.0 / 60.0;
+        while sessions.iter().any(|s| (s.confirmed.frame as usize) < frames) {
+            assert!(started.elapsed() < TIMEOUT * 2, "the match stalled");
+            for (side, session) in sessions.iter_mut().enumerate() {
+                session.tick(dt, scripted(session.frame(), side));
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let [host, guest] = &sessions;
+        assert_eq!(host.replay.frames[..frames], guest.replay.frames[..frames]);
+        //local input shows up INPUT_DELAY frames late
+        for frame in INPUT_DELAY..frames {
+            let expected = [scripted(frame - INPUT_DELAY, 0), scripted(frame - INPUT_DELAY, 1)];
+            assert_eq!(host.replay.frames[frame], expected);
+        }
+        //both confirmed states match a plain run of the agreed inputs
+        for session in &sessions {
+            let replay = &session.replay;
+            let mut plain = Simulation::rotating(replay.arenas.clone(), 11, replay.rules.clone());
+            for commands in &replay.frames {
+                plain.step(dt, commands);
+            }
+            let state = |sim: &Simulation| {
+                let players = sim.players.iter();
+                players.map(|p| format!("{:?} {}", p.pos(), p.entity.hp.0)).collect::<Vec<_>>()
+            };
+            assert_eq!(state(&plain), state(&session.confirmed));
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::weapons;
+use rand::prelude::*;
+use sdl2::rect::Rect;
+
+//hp a health pack gives back, never past the maximum
+pub const HEALTH: f32 = 4.0;
+//seconds a power-up lasts
+pub const POWER_TIME: f32 = 8.0;
+pub const SPEED_BOOST: f32 = 1.5;
+pub const DAMAGE_BOOST: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Power {
+    Speed,
+    Damage,
+    Shield,
+}
+
+impl Power {
+    pub const ALL: [Power; 3] = [Power::Speed, Power::Damage, Power::Shield];
+    pub fn name(self) -> &'static str {
+        match self {
+            Power::Speed => "speed",
+            Power::Damage => "damage",
+            Power::Shield => "shield",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    //a weapon from res/weapons.cfg, by name
+    Weapon(String),
+    Health,
+    Power(Power),
+}
+
+impl Item {
+    pub fn from_name(name: &str) -> Option<Item> {
+        if name == "health" {
+            return Some(Item::Health);
+        }
+        if let Some(power) = Power::ALL.into_iter().find(|p| p.name() == name) {
+            return Some(Item::Power(power));
+        }
+        weapons::find(name).map(|w| Item::Weapon(w.name.clone()))
+    }
+    //texture shown while it waits to be picked up
+    pub fn sprite(&self) -> String {
+        match self {
+            Item::Weapon(name) => weapons::find(name)
+                .map(|w| w.sprite.clone())
+                .unwrap_or_else(|| "gun".to_string()),
+            Item::Health => "pickup_health".to_string(),
+            Item::Power(power) => format!("pickup_{}", power.name()),
+        }
+    }
+}
+
+//a spot in the arena that brings back one of its items a while after it was taken
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spawner {
+    pub x: i32,
+    pub y: i32,
+    //seconds between being emptied and the next item
+    pub every: f32,
+    pub items: Vec<Item>,
+    pub item: Option<Item>,
+    pub timer: f32,
+}
+
+impl Spawner {
+    //starts full, the first item shows up with the match
+    pub fn new(x: i32, y: i32, every: f32, items: Vec<Item>) -> Self {
+        Self {
+            x,
+            y,
+            every,
+            items,
+            item: None,
+            timer: every,
+        }
+    }
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.x, self.y, 3, 3)
+    }
+    pub fn update(&mut self, dt: f32, rng: &mut StdRng) {
+        if self.item.is_some() {
+            return;
+        }
+        self.timer += dt;
+        if self.timer >= self.every {
+            self.item = self.items.choose(rng).cloned();
+        }
+    }
+    pub fn take(&mut self) -> Option<Item> {
+        self.timer = 0.0;
+        self.item.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_taken_item_comes_back_after_a_while() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut spawner = Spawner::new(0, 0, 5.0, vec![Item::Health]);
+        spawner.update(1.0 / 60.0, &mut rng);
+        assert_eq!(spawner.item, Some(Item::Health));
+
+        assert_eq!(spawner.take(), Some(Item::Health));
+        assert_eq!(spawner.item, None);
+        assert_eq!(spawner.take(), None);
+        spawner.update(4.9, &mut rng);
+        assert_eq!(spawner.item, None);
+        spawner.update(0.2, &mut rng);
+        assert_eq!(spawner.item, Some(Item::Health));
+    }
+}
@@ -1,23 +1,27 @@
-use crate::entities::{Entity, Gun};
+use crate::arena::Arena;
+use crate::entities::{Bullet, Entity, Gun};
+use crate::pickups::{Item, Power, DAMAGE_BOOST, HEALTH, POWER_TIME, SPEED_BOOST};
+use crate::simulation::Command;
+use crate::weapons::{self, Weapon};
 
 use crate::{
     components::{Animator, Particle},
     math::Vec2,
-    point, rect,
-    ui::text,
-};
-use sdl2::{
-    pixels::Color,
-    rect::{Point, Rect},
-    render::BlendMode,
-    render::Texture,
-    render::WindowCanvas,
+    rect,
 };
+use sdl2::rect::Rect;
 
 use rand::prelude::*;
 
-use std::collections::HashMap;
+//seconds a player can't be hurt again after a hit
+pub const INVULNERABLE_TIME: f32 = 0.6;
+//seconds a knocked back player may fly faster than they can run
+const STAGGER_TIME: f32 = 0.25;
+//turns a weapon's knockback into a push on the velocity, friction takes it down
+//to roughly the same distance the old direct push moved
+const KNOCKBACK: f32 = 6.0;
 
+#[derive(Clone)]
 pub struct Player {
     pub entity: Entity,
     pub gun: Gun,
@@ -26,11 +30,21 @@ pub struct Player {
     dashing: bool,
     grounded: bool,
     shooting: bool,
-    trail: Vec<Particle>,
+    pub trail: Vec<Particle>,
+    //bits flying off where bullets hit the arena
+    pub sparks: Vec<Particle>,
     pub title: String,
+    pub team: usize,
+    //what the last update did, for sounds
+    pub jumped: bool,
+    pub fired: bool,
+    //seconds left on each power-up, in `Power::ALL` order
+    pub powers: [f32; 3],
+    pub invulnerable: f32,
+    stagger: f32,
 }
 impl Player {
-    pub fn new(x: i32, y: i32, title: &str) -> Self {
+    pub fn new(x: i32, y: i32, title: &str, team: usize) -> Self {
         let mut rect = Rect::new(x, y, 3, 4);
 
         Self {
@@ -40,14 +54,21 @@ impl Player {
                 8.0,
                 Animator::new(rect!(0, 0, rect.w, rect.h), 3, 0.1),
             ),
-            gun: Gun::new(1.0, 0.3),
+            gun: Gun::new(Weapon::starter()),
             dash_timer: 0.0,
             trail_timer: 0.0,
             dashing: false,
             grounded: false,
             shooting: false,
             trail: Vec::new(),
+            sparks: Vec::new(),
             title: title.to_string(),
+            team,
+            jumped: false,
+            fired: false,
+            powers: [0.0; 3],
+            invulnerable: 0.0,
+            stagger: 0.0,
         }
     }
     pub fn pos(&self) -> Vec2 {
@@ -59,50 +80,100 @@ impl Player {
     pub fn title(&self) -> &str {
         &self.title
     }
-    pub fn update(&mut self, dt: f32, controller: [bool; 4], tiles: &Vec<Rect>) {
+    pub fn grounded(&self) -> bool {
+        self.grounded
+    }
+    pub fn has(&self, power: Power) -> bool {
+        self.powers[power as usize] > 0.0
+    }
+    //a bullet landed: pushed along its flight
+    pub fn hit(&mut self, bullet: &Bullet) {
+        self.hurt(bullet.damage, bullet.knockback * bullet.vel.x.signum());
+    }
+    //pushed `push` along x, hurt unless shielded, and untouchable for a moment
+    pub fn hurt(&mut self, damage: f32, push: f32) {
+        self.entity.physics.vel.x += push * KNOCKBACK;
+        self.stagger = STAGGER_TIME;
+        self.invulnerable = INVULNERABLE_TIME;
+        if !self.has(Power::Shield) {
+            self.entity.hp.0 -= damage;
+        }
+    }
+    pub fn collect(&mut self, item: &Item) {
+        match item {
+            Item::Weapon(name) => {
+                if let Some(weapon) = weapons::find(name) {
+                    self.gun.weapon = weapon.clone();
+                }
+            }
+            Item::Health => {
+                self.entity.hp.0 = (self.entity.hp.0 + HEALTH).min(self.entity.hp.1);
+            }
+            Item::Power(power) => self.powers[*power as usize] = POWER_TIME,
+        }
+    }
+    pub fn update(&mut self, dt: f32, command: Command, arena: &Arena, rng: &mut StdRng) {
+        let tiles = &arena.tiles;
+        let bounds = arena.bounds;
         let mut max_vel = Vec2::new(24.0, 120.0);
         let friction = 0.9;
         let mut air_resistance = 0.99;
-        let in_water = self.entity.rect.has_intersection(Rect::new(24, 42, 36, 16));
+        let in_water = arena.in_water(self.entity.rect);
         if in_water {
             air_resistance = 0.8;
         }
         let gravity = 4.0;
+        for time in &mut self.powers {
+            *time = (*time - dt).max(0.0);
+        }
+        self.invulnerable = (self.invulnerable - dt).max(0.0);
+        self.stagger = (self.stagger - dt).max(0.0);
         self.entity.physics.acceleration = Vec2::new(0.0, 0.0);
-        if controller[0] && !controller[1] {
+        if command.right && !command.left {
             self.entity.physics.dir = 1.0;
             self.entity.physics.acceleration.x = 4.0;
         }
 
-        if controller[1] && !controller[0] {
+        if command.left && !command.right {
             self.entity.physics.dir = -1.0;
             self.entity.physics.acceleration.x = 4.0;
         }
-        if controller[2] && (self.grounded || in_water) {
+        if self.has(Power::Speed) {
+            self.entity.physics.acceleration.x *= SPEED_BOOST;
+            max_vel.x *= SPEED_BOOST;
+        }
+        self.jumped = command.jump && (self.grounded || in_water);
+        if self.jumped {
             self.entity.physics.acceleration.y = -max_vel.y;
         }
         // if !self.grounded {
         self.entity.physics.vel.y *= air_resistance;
         // }
-        if controller[3] {
+        if command.shoot {
             self.shooting = true;
         }
+        self.fired = false;
         if self.shooting {
             self.gun.cool_down_timer += dt;
-            if self.gun.cool_down_timer > self.gun.per_bullet_time {
+            if self.gun.cool_down_timer > self.gun.weapon.rate {
                 self.shooting = false;
-                self.gun.bullets.push({
-                    let mut p = Particle::new(
-                        (self.entity.rect.x + 4 * self.entity.physics.dir as i32) as f32,
-                        (self.entity.rect.center().y - 1) as f32,
-                    );
-                    p.acceleration.x *= self.entity.physics.dir;
-                    p
-                });
+                self.fired = true;
+                let boost = if self.has(Power::Damage) {
+                    DAMAGE_BOOST
+                } else {
+                    1.0
+                };
+                self.gun.fire(
+                    (self.entity.rect.x + 4 * self.entity.physics.dir as i32) as f32,
+                    (self.entity.rect.center().y - 1) as f32,
+                    self.entity.physics.dir,
+                    boost,
+                    rng,
+                );
                 self.gun.cool_down_timer = 0.0;
             }
         }
-        // if controller[3] {
+        // if command.shoot {
         //     self.dashing = true;
         //     for i in 0..6 {
         //         self.trail.push(Particle::new(
@@ -127,6 +198,9 @@ impl Player {
         self.entity.physics.vel.x += self.entity.physics.dir * self.entity.physics.acceleration.x;
         self.entity.physics.vel.x *= friction;
         self.entity.physics.vel.x = self.entity.physics.vel.x as i32 as f32;
+        if self.stagger > 0.0 {
+            max_vel.x = max_vel.x.max(self.entity.physics.vel.x.abs());
+        }
         if self.entity.physics.vel.x > max_vel.x {
             self.entity.physics.vel.x = max_vel.x;
         }
@@ -173,22 +247,31 @@ impl Player {
         }
         self.trail.retain_mut(|particle| {
             particle.y +=
-                *[-1.0, 1.0].choose(&mut thread_rng()).unwrap() * particle.acceleration.y * dt;
+                *[-1.0, 1.0].choose(rng).unwrap() * particle.acceleration.y * dt;
             particle.x +=
-                *[-1.0, 1.0].choose(&mut thread_rng()).unwrap() * particle.acceleration.x * dt;
+                *[-1.0, 1.0].choose(rng).unwrap() * particle.acceleration.x * dt;
             particle.alpha -= dt * 400.0;
             particle.alpha > 0.0
         });
+        self.sparks.retain_mut(|spark| {
+            spark.x += spark.acceleration.x * dt;
+            spark.y += spark.acceleration.y * dt;
+            spark.alpha -= dt * 600.0;
+            spark.alpha > 0.0
+        });
+        let sparks = &mut self.sparks;
         self.gun.bullets.retain_mut(|bullet| {
-            bullet.x += bullet.acceleration.x * dt * 10.0;
-            for tile in tiles {
-                if bullet.x < 97.0 && bullet.x > 0.0 && bullet.y > 0.0 && bullet.y < 59.0 {
-                    return true;
-                } else {
-                    return false;
+            if let Some(spot) = bullet.advance(dt, tiles) {
+                for _ in 0..rng.gen_range(3..6) {
+                    sparks.push(Particle::spark(spot, bullet.vel, rng));
                 }
+                return false;
             }
-            return true;
+            bullet.travelled <= bullet.range
+                && bullet.x < bounds.right() as f32
+                && bullet.x > bounds.left() as f32
+                && bullet.y > bounds.top() as f32
+                && bullet.y < bounds.bottom() as f32
         });
         self.entity.animator.animate(dt);
 
@@ -203,6 +286,7 @@ impl Player {
                             - (self.entity.rect.w / 2) * self.entity.physics.dir as i32)
                             as f32,
                         (self.entity.rect.y + (self.entity.rect.h)) as f32,
+                        rng,
                     );
                     p.alpha = 160.0;
                     p
@@ -211,88 +295,23 @@ impl Player {
             }
         }
     }
+}
 
-    pub fn render<'a>(
-        &self,
-        display: &mut WindowCanvas,
-        cache: &mut HashMap<String, Texture<'a>>,
-        offset: (i32, i32),
-    ) {
-        for particle in &self.gun.bullets {
-            display.set_draw_color(Color::RGBA(255, 255, 255, particle.alpha as u8));
-            display.draw_point(point!(
-                particle.x - offset.0 as f32,
-                particle.y - offset.1 as f32
-            ));
-        }
-        display.set_blend_mode(BlendMode::Blend);
-
-        for particle in &self.trail {
-            display.set_draw_color(Color::RGBA(255, 255, 255, particle.alpha as u8));
-            display.draw_point(point!(
-                particle.x - offset.0 as f32,
-                particle.y - offset.1 as f32
-            ));
-        }
-        display.set_blend_mode(BlendMode::None);
-
-        text(
-            display,
-            &self.title,
-            Color::RGB(228, 219, 160),
-            (self.entity.rect.x + self.entity.rect.w / 2) - offset.0,
-            (self.entity.rect.y - 2) - offset.1,
-            0.25,
-        );
-        if self.entity.hp.0 != self.entity.hp.1 {
-            display.set_draw_color(Color::WHITE);
-            display
-                .draw_rect(rect!(
-                    self.entity.rect.x - offset.0,
-                    self.entity.rect.y - 4 - offset.1,
-                    self.entity.hp.1,
-                    1
-                ))
-                .unwrap();
-            if !(self.entity.hp.0 <= 0.0) {
-                display.set_draw_color(Color::GREEN);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                display
-                    .draw_rect(rect!(
-                        self.entity.rect.x - offset.0,
-                        self.entity.rect.y - 4 - offset.1,
-                        self.entity.hp.0,
-                        1
-                    ))
-                    .unwrap();
-            }
+    #[test]
+    fn knockback_into_a_wall_stops_at_the_wall() {
+        let text = "bounds 0 0 60 30\ntile 0 20 60 10\ntile 20 10 2 10\nspawn 23 16\nspawn 40 16\n";
+        let arena = Arena::parse("test.arena", text).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut player = Player::new(23, 16, "p1", 0);
+        player.hurt(0.0, -10.0);
+        for _ in 0..60 {
+            player.update(1.0 / 60.0, Command::default(), &arena, &mut rng);
+            assert!(!player.entity.rect.has_intersection(arena.tiles[1]));
         }
-
-        display.set_draw_color(Color::RGB(139, 181, 212));
-        display.fill_rect(rect!(
-            self.entity.rect.x - offset.0,
-            self.entity.rect.y - offset.1,
-            self.entity.rect.w,
-            self.entity.rect.h
-        ));
-        display
-            .copy_ex(
-                cache.get_mut("gun").unwrap(),
-                None,
-                Rect::new(
-                    (self.entity.rect.x + 4 * self.entity.physics.dir as i32) - offset.0,
-                    ((self.entity.rect.y + self.entity.rect.h / 2) - 1) - offset.1,
-                    4,
-                    2,
-                ),
-                0.0,
-                None,
-                self.entity.physics.dir < 0.0,
-                false,
-            )
-            .unwrap();
-        // display.set_blend_mode(BlendMode::Blend);
-
-        // display.set_blend_mode(BlendMode::None);
+        assert!(player.entity.rect.left() >= 22);
     }
 }
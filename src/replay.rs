@@ -0,0 +1,325 @@
+use crate::arena::Arena;
+use crate::creatures;
+use crate::data::DataError;
+use crate::modes::Mode;
+use crate::simulation::{Command, Rules, MAX_PLAYERS};
+use crate::weapons;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"GLRP";
+const VERSION: u8 = 6;
+//four hours at 60 frames a second, anything longer is a broken file
+const MAX_FRAMES: usize = 60 * 60 * 60 * 4;
+
+//everything needed to re-simulate a match: its seed, arenas, rules and every frame of input.
+//frames are stored as runs of identical input, each player packed into 4 bits
+pub struct Replay {
+    pub seed: u64,
+    //the arenas the rounds take turns on, usually just one
+    pub arenas: Vec<Arena>,
+    pub rules: Rules,
+    pub frames: Vec<Vec<Command>>,
+}
+
+impl Replay {
+    pub fn new(arenas: Vec<Arena>, seed: u64, rules: Rules) -> Self {
+        Self {
+            seed,
+            arenas,
+            rules,
+            frames: Vec::new(),
+        }
+    }
+    pub fn record(&mut self, commands: &[Command]) {
+        self.frames.push(commands.to_vec());
+    }
+    pub fn save(&self, dir: &str) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let path = Path::new(dir).join(format!("{}-{}.replay", secs, self.arenas[0].name));
+        std::fs::write(&path, self.to_bytes())?;
+        Ok(path)
+    }
+    pub fn load(path: &str) -> Result<Self, DataError> {
+        let bytes = std::fs::read(path).map_err(|e| DataError::new(path, None, e.to_string()))?;
+        Self::from_bytes(path, &bytes)
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.extend_from_slice(&self.seed.to_le_bytes());
+        write_arenas(&mut out, &self.arenas);
+        write_rules(&mut out, &self.rules);
+        write_fingerprints(&mut out);
+        write_varint(&mut out, self.frames.len() as u64);
+
+        let mut i = 0;
+        while i < self.frames.len() {
+            let mut run = 1;
+            while i + run < self.frames.len() && self.frames[i + run] == self.frames[i] {
+                run += 1;
+            }
+            write_varint(&mut out, run as u64);
+            out.extend(pack(&self.frames[i], self.rules.players()));
+            i += run;
+        }
+        out
+    }
+    pub fn from_bytes(path: &str, bytes: &[u8]) -> Result<Self, DataError> {
+        let mut reader = Reader { bytes, at: 0, path };
+        if reader.take(4)? != MAGIC {
+            return Err(reader.error("not a replay file"));
+        }
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(reader.error(&format!("unsupported replay version {}", version)));
+        }
+        let seed = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+        let arenas = read_arenas(&mut reader)?;
+        let rules = read_rules(&mut reader)?;
+        let players = rules.players();
+        check_fingerprints(&mut reader, "it would not play back the same")?;
+        let count = reader.varint()? as usize;
+        if count > MAX_FRAMES {
+            return Err(reader.error("corrupt frame count"));
+        }
+
+        let mut frames = Vec::new();
+        while frames.len() < count {
+            let run = reader.varint()? as usize;
+            let commands = unpack(reader.take(players.div_ceil(2))?, players);
+            if run == 0 || frames.len() + run > count {
+                return Err(reader.error("corrupt input run"));
+            }
+            frames.extend(std::iter::repeat_n(commands, run));
+        }
+        Ok(Self {
+            seed,
+            arenas,
+            rules,
+            frames,
+        })
+    }
+}
+
+//each arena by name and source, so a replay plays back even after the files changed.
+//shared with the netplay start packet
+pub fn write_arenas(out: &mut Vec<u8>, arenas: &[Arena]) {
+    write_varint(out, arenas.len() as u64);
+    for arena in arenas {
+        write_str(out, &arena.name);
+        write_str(out, &arena.source);
+    }
+}
+
+pub fn read_arenas(reader: &mut Reader) -> Result<Vec<Arena>, DataError> {
+    let count = reader.varint()? as usize;
+    if count == 0 || count > reader.bytes.len() {
+        return Err(reader.error("corrupt arena count"));
+    }
+    let mut arenas = Vec::new();
+    for _ in 0..count {
+        let name = reader.string()?;
+        let mut arena = Arena::parse(reader.path, &reader.string()?)?;
+        arena.name = name;
+        arenas.push(arena);
+    }
+    Ok(arenas)
+}
+
+//who played and how, shared with the netplay start packet
+pub fn write_rules(out: &mut Vec<u8>, rules: &Rules) {
+    out.push(rules.players() as u8);
+    out.extend(rules.teams.iter().map(|t| *t as u8));
+    out.push(rules.friendly_fire as u8);
+    write_varint(out, rules.rounds as u64);
+    out.extend_from_slice(&rules.round_time.to_le_bytes());
+    out.push(Mode::ALL.iter().position(|m| *m == rules.mode).unwrap() as u8);
+}
+
+pub fn read_rules(reader: &mut Reader) -> Result<Rules, DataError> {
+    let players = reader.take(1)?[0] as usize;
+    if !(2..=MAX_PLAYERS).contains(&players) {
+        return Err(reader.error(&format!("unsupported player count {}", players)));
+    }
+    let teams: Vec<usize> = reader.take(players)?.iter().map(|t| *t as usize).collect();
+    if teams.iter().any(|t| *t >= MAX_PLAYERS) {
+        return Err(reader.error("unsupported team"));
+    }
+    let friendly_fire = reader.take(1)?[0] != 0;
+    let rounds = (reader.varint()? as usize).max(1);
+    let round_time = f32::from_le_bytes(reader.take(4)?.try_into().unwrap());
+    let mode = reader.take(1)?[0] as usize;
+    let mode = match Mode::ALL.get(mode) {
+        Some(mode) => *mode,
+        None => return Err(reader.error(&format!("unknown game mode {}", mode))),
+    };
+    Ok(Rules {
+        teams,
+        friendly_fire,
+        rounds,
+        round_time,
+        mode,
+    })
+}
+
+//the weapons and creatures this game plays with, a match simulated with others would not be the same
+pub fn write_fingerprints(out: &mut Vec<u8>) {
+    out.extend_from_slice(&weapons::fingerprint().to_le_bytes());
+    out.extend_from_slice(&creatures::fingerprint().to_le_bytes());
+}
+
+//`consequence` finishes the error, e.g. what goes wrong if it was played anyway
+pub fn check_fingerprints(reader: &mut Reader, consequence: &str) -> Result<(), DataError> {
+    for (file, fingerprint) in [
+        ("res/weapons.cfg", weapons::fingerprint()),
+        ("res/creatures.cfg", creatures::fingerprint()),
+    ] {
+        if u64::from_le_bytes(reader.take(8)?.try_into().unwrap()) != fingerprint {
+            return Err(reader.error(&format!("made with a different {}, {}", file, consequence)));
+        }
+    }
+    Ok(())
+}
+
+//every replay in `dir`, newest first
+pub fn list_replays(dir: &str) -> Vec<PathBuf> {
+    let mut files: Vec<_> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|f| f.ok())
+            .map(|f| f.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "replay"))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    files.sort();
+    files.reverse();
+    files
+}
+
+fn pack(commands: &[Command], players: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; players.div_ceil(2)];
+    for (i, command) in commands.iter().enumerate().take(players) {
+        bytes[i / 2] |= command.bits() << (4 * (i % 2));
+    }
+    bytes
+}
+
+fn unpack(bytes: &[u8], players: usize) -> Vec<Command> {
+    (0..players)
+        .map(|i| Command::from_bits((bytes[i / 2] >> (4 * (i % 2))) & 0xf))
+        .collect()
+}
+
+pub fn write_str(out: &mut Vec<u8>, s: &str) {
+    write_varint(out, s.len() as u64);
+    out.extend_from_slice(s.as_bytes());
+}
+
+pub fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+pub struct Reader<'a> {
+    pub bytes: &'a [u8],
+    pub at: usize,
+    pub path: &'a str,
+}
+
+impl<'a> Reader<'a> {
+    pub fn error(&self, message: &str) -> DataError {
+        DataError::new(self.path, None, format!("{} (at byte {})", message, self.at))
+    }
+    pub fn take(&mut self, n: usize) -> Result<&'a [u8], DataError> {
+        if self.at + n > self.bytes.len() {
+            return Err(self.error("unexpected end of file"));
+        }
+        let slice = &self.bytes[self.at..self.at + n];
+        self.at += n;
+        Ok(slice)
+    }
+    pub fn varint(&mut self) -> Result<u64, DataError> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(self.error("varint too long"))
+    }
+    pub fn string(&mut self) -> Result<String, DataError> {
+        let len = self.varint()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| self.error("invalid text"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorded() -> Replay {
+        let arena = Arena::load("res/arenas/jungle.arena").unwrap();
+        let mut replay = Replay::new(
+            vec![arena],
+            3,
+            Rules {
+                rounds: 300,
+                ..Rules::free_for_all(3)
+            },
+        );
+        for frame in 0..500 {
+            let shoot = Command {
+                shoot: frame % 7 == 0,
+                ..Command::default()
+            };
+            replay.record(&[shoot, Command::default(), shoot]);
+        }
+        replay
+    }
+
+    #[test]
+    fn replays_read_back_what_was_written() {
+        let replay = recorded();
+        let back = Replay::from_bytes("test", &replay.to_bytes()).unwrap();
+        assert_eq!(back.seed, replay.seed);
+        assert_eq!(back.rules, replay.rules);
+        assert_eq!(back.frames, replay.frames);
+        assert_eq!(back.arenas[0].source, replay.arenas[0].source);
+    }
+
+    #[test]
+    fn replays_from_other_data_or_broken_files_are_refused() {
+        let bytes = recorded().to_bytes();
+        let mut other = bytes.clone();
+        let at = bytes
+            .windows(8)
+            .position(|w| w == weapons::fingerprint().to_le_bytes())
+            .unwrap();
+        other[at] ^= 1;
+        assert!(Replay::from_bytes("test", &other).is_err());
+
+        //the creatures fingerprint follows, then the frame count
+        let mut huge = bytes[..at + 16].to_vec();
+        write_varint(&mut huge, u64::MAX);
+        assert!(Replay::from_bytes("test", &huge).is_err());
+
+        //other versions are refused
+        let mut older = bytes.clone();
+        older[MAGIC.len()] = VERSION - 1;
+        assert!(Replay::from_bytes("test", &older).is_err());
+    }
+}
@@ -0,0 +1,327 @@
+use crate::ai::{Bot, Difficulty};
+use crate::arena::ArenaPicker;
+use crate::args::Args;
+use crate::audio::Audio;
+use crate::controls::Controls;
+use crate::ecosystem::Ecosystem;
+use crate::input::Input;
+use crate::math::Vec2;
+use crate::settings::Settings;
+use crate::simulation::Rules;
+use crate::ui::{button, text, Layout, TextCache};
+use sdl2::{
+    keyboard::Keycode,
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Texture, WindowCanvas},
+};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+
+//seconds to cover the screen and the same again to uncover it
+const TRANSITION: f32 = 0.25;
+
+//what the menu set up for the next local match
+pub struct Setup {
+    pub rules: Rules,
+    pub teams: bool,
+    pub opponent: Option<Difficulty>,
+}
+
+//everything scenes share, handed to every hook
+pub struct Game<'a, 'ttf, 't> {
+    pub display: WindowCanvas,
+    pub cache: HashMap<String, Texture<'a>>,
+    pub texts: TextCache<'ttf, 't>,
+    pub input: Input,
+    pub layout: Layout,
+    pub camera: Vec2,
+    pub zoom: f32,
+    pub args: Args,
+    pub arenas: ArenaPicker,
+    pub controls: Controls,
+    pub controls_path: PathBuf,
+    pub settings: Settings,
+    pub settings_path: PathBuf,
+    pub audio: Audio,
+    pub setup: Setup,
+    //a quit confirmation is already showing
+    pub quitting: bool,
+}
+
+impl Game<'_, '_, '_> {
+    pub fn frame(&self) -> Rect {
+        self.layout.frame()
+    }
+    //the window size in arena pixels
+    pub fn win_dim(&self) -> (i32, i32) {
+        (
+            (self.display.window().size().0 as f32 / self.zoom) as i32,
+            (self.display.window().size().1 as f32 / self.zoom) as i32,
+        )
+    }
+    pub fn world_view(&mut self) {
+        let (w, h) = self.win_dim();
+        self.display.set_logical_size(w as u32, h as u32).unwrap();
+    }
+    pub fn menu_view(&mut self) {
+        let frame = self.frame();
+        self.display
+            .set_logical_size(frame.w as u32, frame.h as u32)
+            .unwrap();
+    }
+    pub fn text(&mut self, label: &str, color: Color, x: i32, y: i32, factor: f32) {
+        text(
+            &mut self.display,
+            &mut self.texts,
+            label,
+            color,
+            x,
+            y,
+            factor,
+        );
+    }
+    pub fn button(&mut self, label: &str, x: i32, y: i32, factor: f32) -> bool {
+        button(
+            &mut self.display,
+            &mut self.texts,
+            label,
+            self.layout.frame_color(),
+            x,
+            y,
+            &self.input,
+            factor,
+        )
+    }
+    //the `<` in the top left corner every sub screen has, escape works too
+    pub fn back_button(&mut self) -> bool {
+        self.button("<", 9, 9, 0.5) || self.input.is_key_clicked(Keycode::Escape)
+    }
+    //against bots only p1 is human, everyone else is driven by one
+    pub fn start_match(&mut self) -> Ecosystem {
+        let seed = self.args.seed();
+        let rules = self.setup.rules.clone();
+        let players = rules.players();
+        let mut ecosystem = Ecosystem::new(self.arenas.pick(), seed, rules);
+        if let Some(difficulty) = self.setup.opponent {
+            for player in 1..players {
+                ecosystem.bots.push(Bot::new(player, difficulty, seed));
+            }
+        }
+        ecosystem
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Effect {
+    Cut,
+    Fade,
+    Wipe,
+}
+
+pub enum Change {
+    Stay,
+    //pushed scenes sit on top of the current one, e.g. the pause overlay
+    Push(Box<dyn Scene>),
+    Pop,
+    Replace(Box<dyn Scene>, Effect),
+    //drops the whole stack, e.g. quitting to the menu from the pause overlay
+    Reset(Box<dyn Scene>, Effect),
+}
+
+//one screen of the game.
+//`render` draws whatever should stay visible under an overlay or during a transition,
+//`update` runs only for the top scene and also draws its buttons, since they check clicks as they draw
+pub trait Scene {
+    fn enter(&mut self, _game: &mut Game) {}
+    fn exit(&mut self, _game: &mut Game) {}
+    fn update(&mut self, game: &mut Game, dt: f32) -> Change;
+    fn render(&mut self, _game: &mut Game) {}
+    //keeps the scene below visible, frozen, instead of covering it
+    fn overlay(&self) -> bool {
+        false
+    }
+}
+
+struct Transition {
+    effect: Effect,
+    time: f32,
+    //applied once the screen is fully covered
+    change: Option<Change>,
+}
+
+pub struct Scenes {
+    stack: Vec<Box<dyn Scene>>,
+    transition: Option<Transition>,
+    //changes asked for while a transition plays, applied once it is over
+    queued: VecDeque<Change>,
+}
+
+impl Scenes {
+    pub fn new(first: Box<dyn Scene>, game: &mut Game) -> Self {
+        let mut scenes = Self {
+            stack: Vec::new(),
+            transition: None,
+            queued: VecDeque::new(),
+        };
+        scenes.apply(Change::Push(first), game);
+        scenes
+    }
+    //puts a scene on top from outside, e.g. when the window is closed
+    pub fn push(&mut self, scene: Box<dyn Scene>, game: &mut Game) {
+        self.apply(Change::Push(scene), game);
+    }
+    pub fn frame(&mut self, game: &mut Game, dt: f32) {
+        let bottom = self.stack.iter().rposition(|s| !s.overlay()).unwrap_or(0);
+        for scene in &mut self.stack[bottom..] {
+            game.menu_view();
+            scene.render(game);
+        }
+        let change = match self.stack.last_mut() {
+            Some(top) => {
+                game.menu_view();
+                top.update(game, dt)
+            }
+            None => Change::Stay,
+        };
+        for change in self.schedule(change, dt) {
+            self.apply(change, game);
+        }
+        self.render_transition(game);
+    }
+    //the changes due this frame.
+    //the top scene keeps running while a transition plays, whatever it asks for meanwhile waits for the transition to end
+    fn schedule(&mut self, change: Change, dt: f32) -> Vec<Change> {
+        let mut due = Vec::new();
+        if !matches!(change, Change::Stay) {
+            self.queued.push_back(change);
+        }
+        if let Some(transition) = &mut self.transition {
+            transition.time += dt;
+            if transition.time >= TRANSITION {
+                if let Some(change) = transition.change.take() {
+                    due.push(change);
+                }
+            }
+            if transition.time < TRANSITION * 2.0 {
+                return due;
+            }
+            self.transition = None;
+        }
+        while let Some(change) = self.queued.pop_front() {
+            match change {
+                Change::Replace(_, effect) | Change::Reset(_, effect) if effect != Effect::Cut => {
+                    self.transition = Some(Transition {
+                        effect,
+                        time: 0.0,
+                        change: Some(change),
+                    });
+                    break;
+                }
+                change => due.push(change),
+            }
+        }
+        due
+    }
+    fn apply(&mut self, change: Change, game: &mut Game) {
+        match change {
+            Change::Stay => {}
+            Change::Push(mut scene) => {
+                scene.enter(game);
+                self.stack.push(scene);
+            }
+            Change::Pop => {
+                if let Some(mut scene) = self.stack.pop() {
+                    scene.exit(game);
+                }
+            }
+            Change::Replace(scene, _) => {
+                self.apply(Change::Pop, game);
+                self.apply(Change::Push(scene), game);
+            }
+            Change::Reset(scene, _) => {
+                while !self.stack.is_empty() {
+                    self.apply(Change::Pop, game);
+                }
+                self.apply(Change::Push(scene), game);
+            }
+        }
+    }
+    //covers the screen on the way out and uncovers it on the way in
+    fn render_transition(&self, game: &mut Game) {
+        let transition = match &self.transition {
+            Some(transition) => transition,
+            None => return,
+        };
+        game.menu_view();
+        let frame = game.frame();
+        let covered = 1.0 - (transition.time / TRANSITION - 1.0).abs();
+        game.display.set_blend_mode(BlendMode::Blend);
+        game.display.set_draw_color(Color::RGBA(0, 0, 0, 255));
+        match transition.effect {
+            Effect::Cut => {}
+            Effect::Fade => {
+                game.display
+                    .set_draw_color(Color::RGBA(0, 0, 0, (covered * 255.0) as u8));
+                game.display.fill_rect(frame).unwrap();
+            }
+            //sweeps in from the left, then off to the right
+            Effect::Wipe => {
+                let w = (frame.w as f32 * covered) as i32;
+                let x = if transition.time < TRANSITION {
+                    0
+                } else {
+                    frame.w - w
+                };
+                game.display
+                    .fill_rect(Rect::new(x, 0, w as u32, frame.h as u32))
+                    .unwrap();
+            }
+        }
+        game.display.set_blend_mode(BlendMode::None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Blank;
+    impl Scene for Blank {
+        fn update(&mut self, _game: &mut Game, _dt: f32) -> Change {
+            Change::Stay
+        }
+    }
+
+    fn names(changes: &[Change]) -> Vec<&'static str> {
+        changes
+            .iter()
+            .map(|change| match change {
+                Change::Stay => "stay",
+                Change::Push(_) => "push",
+                Change::Pop => "pop",
+                Change::Replace(..) => "replace",
+                Change::Reset(..) => "reset",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn changes_during_a_transition_wait_for_it_to_end() {
+        let mut scenes = Scenes {
+            stack: Vec::new(),
+            transition: None,
+            queued: VecDeque::new(),
+        };
+        let step = TRANSITION / 2.0 + 0.01;
+        let fade = Change::Replace(Box::new(Blank), Effect::Fade);
+        assert!(names(&scenes.schedule(fade, step)).is_empty());
+        //asked for while the screen is still covering
+        assert!(names(&scenes.schedule(Change::Push(Box::new(Blank)), step)).is_empty());
+        assert_eq!(names(&scenes.schedule(Change::Stay, step)), ["replace"]);
+        assert!(names(&scenes.schedule(Change::Pop, step)).is_empty());
+        assert_eq!(names(&scenes.schedule(Change::Stay, step)), ["push", "pop"]);
+        assert!(scenes.transition.is_none());
+        assert_eq!(names(&scenes.schedule(Change::Pop, step)), ["pop"]);
+    }
+}
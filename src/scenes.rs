@@ -0,0 +1,1021 @@
+use crate::ai::Difficulty;
+use crate::controls::{Action, Controls};
+use crate::data::config_path;
+use crate::draw;
+use crate::ecosystem::{read_commands, Ecosystem, Rematch};
+use crate::math::sine_wave;
+use crate::modes::Mode;
+use crate::netplay::{Lobby, Session};
+use crate::rect;
+use crate::replay::{list_replays, Replay};
+use crate::scene::{Change, Effect, Game, Scene};
+use crate::scores::{HighScores, Run};
+use crate::settings::{cycle, SCALES, ZOOMS};
+use crate::simulation::{Rules, MAX_PLAYERS, ROUNDS, ROUND_TIME};
+use crate::ui::text_ex;
+use crate::viewer::Viewer;
+use sdl2::{keyboard::Keycode, pixels::Color, rect::Rect, render::BlendMode};
+use std::path::PathBuf;
+
+//the round settings carry over from `old`, survival is always everyone together
+fn menu_rules(players: usize, teams: bool, friendly_fire: bool, old: &Rules) -> Rules {
+    if old.mode == Mode::Survival {
+        return Rules::co_op(players);
+    }
+    let mut rules = if teams {
+        Rules::two_teams(players, friendly_fire)
+    } else {
+        Rules::free_for_all(players)
+    };
+    rules.rounds = old.rounds;
+    rules.round_time = old.round_time;
+    rules.mode = old.mode;
+    rules
+}
+
+pub struct Intro {
+    current_time: f32,
+}
+impl Intro {
+    pub fn new() -> Self {
+        Self { current_time: 0.0 }
+    }
+}
+impl Scene for Intro {
+    fn enter(&mut self, game: &mut Game) {
+        game.audio.play_list("menu");
+    }
+    fn update(&mut self, _game: &mut Game, dt: f32) -> Change {
+        self.current_time += dt * 8.0;
+        if self.current_time >= 24.0 {
+            Change::Replace(Box::new(Menu), Effect::Fade)
+        } else {
+            Change::Stay
+        }
+    }
+    fn render(&mut self, game: &mut Game) {
+        let frame = game.frame();
+        game.display.set_blend_mode(BlendMode::Blend);
+        game.text(
+            "zealous",
+            Color::RGBA(
+                255,
+                255,
+                255,
+                sine_wave(self.current_time, 50.0, 255.0, 1.0) as u8,
+            ),
+            frame.w / 2,
+            frame.h / 2,
+            0.5,
+        );
+        let t = game.cache.get_mut("logo").unwrap();
+        t.set_alpha_mod((sine_wave(self.current_time, 50.0, 255.0, 1.0)) as u8);
+        game.display
+            .copy(
+                t,
+                None,
+                Rect::new(
+                    (frame.w as f32 / 1.8) as i32,
+                    (frame.h as f32 / 2.5) as i32,
+                    7,
+                    5,
+                ),
+            )
+            .unwrap();
+    }
+}
+
+pub struct Menu;
+impl Scene for Menu {
+    fn enter(&mut self, game: &mut Game) {
+        game.audio.play_list("menu");
+    }
+    fn update(&mut self, game: &mut Game, _dt: f32) -> Change {
+        let frame = game.frame();
+        if game.input.is_key_clicked(Keycode::Escape) {
+            return Change::Push(Box::new(Quit));
+        }
+        game.text(
+            "GladArena",
+            Color::RGB(168, 181, 174),
+            frame.w / 2,
+            frame.h / 10,
+            0.5,
+        );
+        if game.button("settings", frame.w - 12, 9, 0.25) {
+            return Change::Push(Box::new(SettingsScreen::new()));
+        }
+        let preview = rect!(frame.w / 2 - 12, 12, 24, 15);
+        match game.arenas.preview() {
+            Some(arena) => draw::arena_preview(&mut game.display, arena, preview),
+            None => game.text(
+                "?",
+                Color::RGB(241, 234, 182),
+                preview.center().x,
+                preview.center().y,
+                0.5,
+            ),
+        }
+        game.display.set_draw_color(game.layout.frame_color());
+        game.display.draw_rect(preview).unwrap();
+        if game.button("<", preview.left() - 6, preview.center().y, 0.5) {
+            game.arenas.cycle(-1);
+        }
+        if game.button(">", preview.right() + 6, preview.center().y, 0.5) {
+            game.arenas.cycle(1);
+        }
+        let survival = game.setup.rules.mode == Mode::Survival;
+        let best_of = format!("best of {}", game.setup.rules.best_of());
+        if !survival && game.button(&best_of, frame.w * 7 / 8, preview.center().y, 0.25) {
+            //best of 1, 3, 5 or 7
+            let rules = &mut game.setup.rules;
+            rules.rounds = rules.rounds % 4 + 1;
+        }
+        let mode = game.setup.rules.mode;
+        if game.button(mode.name(), frame.w / 8, preview.center().y, 0.25) {
+            //deathmatch, king of the hill, capture the flag, survival
+            let next = Mode::ALL.iter().position(|m| *m == mode).unwrap() + 1;
+            let setup = &mut game.setup;
+            let mut old = setup.rules.clone();
+            //survival plays a single round, the others start over from the usual match
+            if mode == Mode::Survival {
+                old.rounds = ROUNDS;
+                old.round_time = ROUND_TIME;
+            }
+            old.mode = Mode::ALL[next % Mode::ALL.len()];
+            let friendly_fire = setup.rules.friendly_fire;
+            setup.rules = menu_rules(old.players(), setup.teams, friendly_fire, &old);
+        }
+        //koth and ctf need a hill or enough flags, otherwise the match is a deathmatch
+        let sides = game.setup.rules.sides();
+        let mode = game.setup.rules.mode;
+        let unfit = game
+            .arenas
+            .preview()
+            .is_some_and(|a| mode.check(a, sides).is_err());
+        if unfit {
+            let note = match mode {
+                Mode::KingOfTheHill => "no hill here",
+                _ => "too few flags",
+            };
+            game.text(
+                note,
+                Color::RGB(212, 139, 139),
+                frame.w / 8,
+                preview.center().y + 5,
+                0.25,
+            );
+        }
+        let label = game.arenas.label().to_string();
+        game.text(
+            &label,
+            Color::RGB(241, 234, 182),
+            frame.w / 2,
+            preview.bottom() + 3,
+            0.25,
+        );
+        let options = preview.bottom() + 9;
+        let setup = &game.setup;
+        let players = format!("{} players", setup.rules.players());
+        let mode = match (setup.teams, setup.rules.friendly_fire) {
+            _ if setup.rules.mode == Mode::Survival => "co-op",
+            (false, _) => "ffa",
+            (true, false) => "teams",
+            (true, true) => "teams+ff",
+        };
+        let opponent = match setup.opponent {
+            Some(difficulty) => format!("vs bot: {}", difficulty.name()),
+            None => "vs human".to_string(),
+        };
+        if game.button(&players, frame.w / 5, options, 0.25) {
+            let setup = &mut game.setup;
+            let players = setup.rules.players() % MAX_PLAYERS + 1;
+            let friendly_fire = setup.rules.friendly_fire;
+            setup.rules = menu_rules(players.max(2), setup.teams, friendly_fire, &setup.rules);
+        }
+        if game.button(mode, frame.w * 4 / 5, options, 0.25) && !survival {
+            //free for all, teams, teams with friendly fire
+            let setup = &mut game.setup;
+            let (teams, friendly_fire) = match (setup.teams, setup.rules.friendly_fire) {
+                (false, _) => (true, false),
+                (true, false) => (true, true),
+                (true, true) => (false, false),
+            };
+            setup.teams = teams;
+            setup.rules = menu_rules(setup.rules.players(), teams, friendly_fire, &setup.rules);
+        }
+        if game.button(&opponent, frame.w / 2, options, 0.25) {
+            game.setup.opponent = match game.setup.opponent {
+                None => Some(Difficulty::Easy),
+                Some(Difficulty::Easy) => Some(Difficulty::Normal),
+                Some(Difficulty::Normal) => Some(Difficulty::Hard),
+                Some(Difficulty::Hard) => None,
+            };
+        }
+        if game.button("play", frame.w / 2, (frame.h as f32 / 1.3) as i32, 0.5) {
+            let ecosystem = game.start_match();
+            return Change::Replace(Box::new(Play::new(ecosystem, None)), Effect::Fade);
+        }
+        let row = (frame.h as f32 / 1.09) as i32;
+        if game.button("online", frame.w / 8, row, 0.25) {
+            return Change::Replace(Box::new(Online::new(None)), Effect::Wipe);
+        }
+        if game.button("replays", frame.w * 3 / 8, row, 0.25) {
+            return Change::Replace(Box::new(Replays::new()), Effect::Wipe);
+        }
+        if game.button("controls", frame.w * 5 / 8, row, 0.25) {
+            return Change::Replace(Box::new(ControlsScreen::new()), Effect::Wipe);
+        }
+        if game.button("credits", frame.w * 7 / 8, row, 0.25) {
+            return Change::Replace(Box::new(Credits), Effect::Wipe);
+        }
+        Change::Stay
+    }
+}
+
+pub struct Credits;
+impl Scene for Credits {
+    fn update(&mut self, game: &mut Game, _dt: f32) -> Change {
+        if game.back_button() {
+            Change::Replace(Box::new(Menu), Effect::Wipe)
+        } else {
+            Change::Stay
+        }
+    }
+    fn render(&mut self, game: &mut Game) {
+        let frame = game.frame();
+        text_ex(
+            &mut game.display,
+            &mut game.texts,
+            vec![
+                ("discord: ", Color::RGB(146, 146, 156)),
+                ("zealous_carrot#9124", Color::RGB(241, 234, 182)),
+            ],
+            frame.w / 8,
+            frame.h / 4,
+            0.25,
+        );
+        text_ex(
+            &mut game.display,
+            &mut game.texts,
+            vec![
+                ("github: ", Color::RGB(146, 146, 156)),
+                ("..github/zealous_carrot..", Color::RGB(241, 234, 182)),
+            ],
+            frame.w / 8,
+            frame.h / 3,
+            0.25,
+        );
+    }
+}
+
+pub struct Play {
+    ecosystem: Ecosystem,
+    session: Option<Session>,
+    //set once the result is in; the match stays on screen while fading out
+    over: bool,
+}
+impl Play {
+    pub fn new(ecosystem: Ecosystem, session: Option<Session>) -> Self {
+        Self {
+            ecosystem,
+            session,
+            over: false,
+        }
+    }
+}
+impl Scene for Play {
+    fn enter(&mut self, game: &mut Game) {
+        game.audio.play_arena(&self.ecosystem.sim.arena);
+    }
+    fn update(&mut self, game: &mut Game, dt: f32) -> Change {
+        if self.over {
+            return Change::Stay;
+        }
+        let win_dim = game.win_dim();
+        self.ecosystem.follow(&mut game.camera, win_dim, dt);
+        let over = match &mut self.session {
+            Some(session) => {
+                let keys = read_commands(&game.input, &game.controls);
+                if let Some(predicted) = session.tick(dt, keys[0].merge(keys[1])) {
+                    self.ecosystem.show(predicted, dt, &mut game.camera);
+                }
+                if session.confirmed.is_over() {
+                    self.ecosystem.sim = session.confirmed.clone();
+                }
+                session.confirmed.is_over()
+            }
+            None => {
+                self.ecosystem
+                    .update(dt, &game.input, &game.controls, &mut game.camera);
+                self.ecosystem.sim.is_over()
+            }
+        };
+        game.audio.react(&self.ecosystem.sim);
+        if over {
+            self.over = true;
+            let replay = match &self.session {
+                Some(session) => &session.replay,
+                None => &self.ecosystem.replay,
+            };
+            match replay.save("replays") {
+                Ok(path) => println!("replay saved to {}", path.display()),
+                Err(e) => eprintln!("could not save replay: {}", e),
+            }
+            let sim = &self.ecosystem.sim;
+            let over = match (sim.objective.summary(), sim.objective.progress()) {
+                (Some(summary), Some((wave, kills))) => {
+                    let run = Run {
+                        wave,
+                        kills,
+                        players: sim.rules.players(),
+                        arena: sim.arena.name.clone(),
+                    };
+                    let (rank, board) = record_run(run);
+                    Over::new(summary, rank, board, sim.seed, self.session.take())
+                }
+                _ => {
+                    let score: Vec<String> = sim
+                        .standings()
+                        .iter()
+                        .map(|(side, rounds)| format!("{} {}", side, rounds))
+                        .collect();
+                    Over::new(
+                        format!("{:?} won!", sim.winner()),
+                        score.join("  "),
+                        Vec::new(),
+                        sim.seed,
+                        self.session.take(),
+                    )
+                }
+            };
+            return Change::Replace(Box::new(over), Effect::Fade);
+        }
+        if self.session.as_ref().is_some_and(|s| s.disconnected) {
+            self.session = None;
+            return Change::Replace(
+                Box::new(Online::new(Some("opponent left".to_string()))),
+                Effect::Fade,
+            );
+        }
+        //an online match keeps running on the other side, so it cannot pause
+        if self.session.is_none() && game.controls.paused(&game.input) {
+            return Change::Push(Box::new(Pause::new(self.ecosystem.rematch())));
+        }
+        Change::Stay
+    }
+    fn render(&mut self, game: &mut Game) {
+        game.world_view();
+        self.ecosystem.render(
+            &mut game.display,
+            &mut game.cache,
+            &mut game.texts,
+            game.camera,
+        );
+        let dim = game.win_dim();
+        self.ecosystem
+            .render_hud(&mut game.display, &mut game.texts, dim);
+    }
+}
+
+pub struct Pause {
+    rematch: Rematch,
+}
+impl Pause {
+    pub fn new(rematch: Rematch) -> Self {
+        Self { rematch }
+    }
+}
+impl Scene for Pause {
+    fn update(&mut self, game: &mut Game, _dt: f32) -> Change {
+        let frame = game.frame();
+        if game.controls.paused(&game.input) {
+            return Change::Pop;
+        }
+        if game.button("resume", frame.w / 2, frame.h / 5, 0.5) {
+            return Change::Pop;
+        }
+        if game.button("restart", frame.w / 2, frame.h * 2 / 5, 0.5) {
+            let ecosystem = self.rematch.start();
+            return Change::Reset(Box::new(Play::new(ecosystem, None)), Effect::Fade);
+        }
+        if game.button("settings", frame.w / 2, frame.h * 3 / 5, 0.5) {
+            return Change::Push(Box::new(SettingsScreen::new()));
+        }
+        if game.button("menu", frame.w / 2, frame.h * 4 / 5, 0.5) {
+            return Change::Reset(Box::new(Menu), Effect::Fade);
+        }
+        Change::Stay
+    }
+    //dims the frozen match underneath
+    fn render(&mut self, game: &mut Game) {
+        dim(game);
+        let frame = game.frame();
+        game.text(
+            &format!("seed {}", self.rematch.seed()),
+            Color::RGB(168, 181, 174),
+            frame.w / 2,
+            frame.h - 4,
+            0.25,
+        );
+    }
+    fn overlay(&self) -> bool {
+        true
+    }
+}
+
+//asked before leaving the game, closing the window a second time skips it
+pub struct Quit;
+impl Scene for Quit {
+    fn enter(&mut self, game: &mut Game) {
+        game.quitting = true;
+    }
+    fn exit(&mut self, game: &mut Game) {
+        game.quitting = false;
+    }
+    fn update(&mut self, game: &mut Game, _dt: f32) -> Change {
+        let frame = game.frame();
+        if game.input.close_requested() {
+            game.input.exit();
+        }
+        if game.input.is_key_clicked(Keycode::Escape) {
+            return Change::Pop;
+        }
+        if game.button("quit", frame.w / 3, frame.h * 3 / 5, 0.5) {
+            game.input.exit();
+        }
+        if game.button("stay", frame.w * 2 / 3, frame.h * 3 / 5, 0.5) {
+            return Change::Pop;
+        }
+        Change::Stay
+    }
+    fn render(&mut self, game: &mut Game) {
+        dim(game);
+        let frame = game.frame();
+        game.text(
+            "quit the game?",
+            Color::RGB(241, 234, 182),
+            frame.w / 2,
+            frame.h / 3,
+            0.5,
+        );
+    }
+    fn overlay(&self) -> bool {
+        true
+    }
+}
+
+fn dim(game: &mut Game) {
+    let frame = game.frame();
+    game.display.set_blend_mode(BlendMode::Blend);
+    game.display.set_draw_color(Color::RGBA(0, 0, 0, 160));
+    game.display.fill_rect(frame).unwrap();
+    game.display.set_blend_mode(BlendMode::None);
+}
+
+//how many of the best runs fit under the buttons
+const BOARD_ROWS: usize = 3;
+
+//adds a finished survival run to the high scores, returns how it placed and the best runs
+fn record_run(run: Run) -> (String, Vec<String>) {
+    let path = config_path("scores.cfg");
+    let mut scores = HighScores::load(&path);
+    let rank = scores.record(run);
+    if rank.is_some() {
+        if let Err(e) = scores.save(&path) {
+            eprintln!("could not save high scores: {}", e);
+        }
+    }
+    let placed = match rank {
+        Some(0) => "a new best run!".to_string(),
+        Some(rank) => format!("#{} of the best runs", rank + 1),
+        None => "not one of the best runs".to_string(),
+    };
+    let board = scores
+        .runs
+        .iter()
+        .take(BOARD_ROWS)
+        .enumerate()
+        .map(|(i, run)| format!("{}. {}", i + 1, run.describe()))
+        .collect();
+    (placed, board)
+}
+
+pub struct Over {
+    title: String,
+    //rounds won by every side, or where a survival run placed
+    score: String,
+    //the best survival runs so far
+    board: Vec<String>,
+    //pass it to `--seed` to play the same randomness again
+    seed: u64,
+    session: Option<Session>,
+}
+impl Over {
+    pub fn new(
+        title: String,
+        score: String,
+        board: Vec<String>,
+        seed: u64,
+        session: Option<Session>,
+    ) -> Self {
+        Self {
+            title,
+            score,
+            board,
+            seed,
+            session,
+        }
+    }
+}
+impl Scene for Over {
+    fn exit(&mut self, _game: &mut Game) {
+        if let Some(mut session) = self.session.take() {
+            session.leave();
+        }
+    }
+    fn update(&mut self, game: &mut Game, _dt: f32) -> Change {
+        let frame = game.frame();
+        if let Some(session) = &mut self.session {
+            session.linger();
+        }
+        let online = self.session.is_some();
+        if game.button(
+            if online { "online" } else { "retry" },
+            frame.w / 2,
+            (frame.h as f32 / 2.5) as i32,
+            0.5,
+        ) {
+            return if online {
+                Change::Replace(Box::new(Online::new(None)), Effect::Wipe)
+            } else {
+                let ecosystem = game.start_match();
+                Change::Replace(Box::new(Play::new(ecosystem, None)), Effect::Fade)
+            };
+        }
+        if game.button("menu", frame.w / 2, (frame.h as f32 / 1.5) as i32, 0.5) {
+            return Change::Replace(Box::new(Menu), Effect::Fade);
+        }
+        Change::Stay
+    }
+    fn render(&mut self, game: &mut Game) {
+        let frame = game.frame();
+        game.text(
+            &self.title,
+            Color::RGB(241, 234, 182),
+            frame.w / 2,
+            frame.h / 4,
+            0.5,
+        );
+        game.text(
+            &self.score,
+            Color::RGB(168, 181, 174),
+            frame.w / 2,
+            frame.h / 4 + 6,
+            0.25,
+        );
+        game.text(
+            &format!("seed {}", self.seed),
+            Color::RGB(168, 181, 174),
+            frame.w / 2,
+            frame.h / 4 + 11,
+            0.25,
+        );
+        for (i, line) in self.board.iter().enumerate() {
+            game.text(
+                line,
+                Color::RGB(168, 181, 174),
+                frame.w / 2,
+                frame.h * 4 / 5 + i as i32 * 4,
+                0.25,
+            );
+        }
+    }
+}
+
+pub struct Replays {
+    replays: Vec<PathBuf>,
+    index: usize,
+}
+impl Replays {
+    pub fn new() -> Self {
+        Self {
+            replays: Vec::new(),
+            index: 0,
+        }
+    }
+}
+impl Scene for Replays {
+    //a match may have been saved since the list was last shown
+    fn enter(&mut self, _game: &mut Game) {
+        self.replays = list_replays("replays");
+        self.index = 0;
+    }
+    fn update(&mut self, game: &mut Game, _dt: f32) -> Change {
+        let frame = game.frame();
+        if game.back_button() {
+            return Change::Replace(Box::new(Menu), Effect::Wipe);
+        }
+        if self.replays.is_empty() {
+            return Change::Stay;
+        }
+        let y = (frame.h as f32 / 2.4) as i32;
+        let len = self.replays.len();
+        if game.button("<", 12, y, 0.5) {
+            self.index = (self.index + len - 1) % len;
+        }
+        if game.button(">", frame.w - 12, y, 0.5) {
+            self.index = (self.index + 1) % len;
+        }
+        let path = &self.replays[self.index];
+        game.text(
+            &path.file_stem().unwrap().to_string_lossy(),
+            Color::RGB(241, 234, 182),
+            frame.w / 2,
+            y,
+            0.25,
+        );
+        if game.button("watch", frame.w / 2, (frame.h as f32 / 1.4) as i32, 0.5) {
+            match Replay::load(path.to_str().unwrap()) {
+                Ok(replay) => {
+                    return Change::Replace(
+                        Box::new(ReplayViewer::new(Viewer::new(replay))),
+                        Effect::Fade,
+                    )
+                }
+                Err(e) => eprintln!("could not load replay: {}", e),
+            }
+        }
+        Change::Stay
+    }
+    fn render(&mut self, game: &mut Game) {
+        let frame = game.frame();
+        game.text(
+            "replays",
+            Color::RGB(168, 181, 174),
+            frame.w / 2,
+            frame.h / 6,
+            0.5,
+        );
+        if self.replays.is_empty() {
+            game.text(
+                "no replays yet",
+                Color::RGB(146, 146, 156),
+                frame.w / 2,
+                frame.h / 2,
+                0.25,
+            );
+        }
+    }
+}
+
+pub struct ReplayViewer {
+    viewer: Viewer,
+}
+impl ReplayViewer {
+    pub fn new(viewer: Viewer) -> Self {
+        Self { viewer }
+    }
+}
+impl Scene for ReplayViewer {
+    fn enter(&mut self, game: &mut Game) {
+        game.audio.play_arena(&self.viewer.ecosystem.sim.arena);
+    }
+    fn update(&mut self, game: &mut Game, dt: f32) -> Change {
+        let win_dim = game.win_dim();
+        self.viewer.ecosystem.follow(&mut game.camera, win_dim, dt);
+        self.viewer.update(dt, &game.input, &mut game.camera);
+        game.audio.react(&self.viewer.ecosystem.sim);
+        if game.back_button() {
+            Change::Replace(Box::new(Replays::new()), Effect::Fade)
+        } else {
+            Change::Stay
+        }
+    }
+    fn render(&mut self, game: &mut Game) {
+        game.world_view();
+        let dim = game.win_dim();
+        self.viewer.render(
+            &mut game.display,
+            &mut game.cache,
+            &mut game.texts,
+            game.camera,
+            dim,
+        );
+    }
+}
+
+pub struct Online {
+    lobby: Option<Lobby>,
+    status: String,
+}
+impl Online {
+    pub fn new(status: Option<String>) -> Self {
+        Self {
+            lobby: None,
+            status: status.unwrap_or_default(),
+        }
+    }
+    //for --host and --join, which open the lobby before the window does
+    pub fn waiting(lobby: Lobby) -> Self {
+        Self {
+            lobby: Some(lobby),
+            status: String::new(),
+        }
+    }
+}
+impl Scene for Online {
+    fn update(&mut self, game: &mut Game, _dt: f32) -> Change {
+        let frame = game.frame();
+        if game.back_button() {
+            self.lobby = None;
+            return Change::Replace(Box::new(Menu), Effect::Wipe);
+        }
+        match &mut self.lobby {
+            None => {
+                if game.button("host", frame.w / 2, (frame.h as f32 / 2.5) as i32, 0.5) {
+                    match Lobby::host(
+                        game.args.port,
+                        game.arenas.pick(),
+                        game.args.seed(),
+                        game.setup.rules.clone(),
+                    ) {
+                        Ok(started) => self.lobby = Some(started),
+                        Err(e) => self.status = e.to_string(),
+                    }
+                }
+                if game.button("join", frame.w / 2, (frame.h as f32 / 1.7) as i32, 0.5) {
+                    match Lobby::join(&game.args.peer()) {
+                        Ok(started) => self.lobby = Some(started),
+                        Err(e) => self.status = e.to_string(),
+                    }
+                }
+            }
+            Some(waiting) => {
+                game.text(
+                    &waiting.status(),
+                    Color::RGB(241, 234, 182),
+                    frame.w / 2,
+                    frame.h / 2,
+                    0.25,
+                );
+                if let Some(started) = waiting.poll() {
+                    let ecosystem = Ecosystem::new(
+                        started.confirmed.arenas(),
+                        started.seed(),
+                        started.confirmed.rules.clone(),
+                    );
+                    self.lobby = None;
+                    return Change::Replace(
+                        Box::new(Play::new(ecosystem, Some(started))),
+                        Effect::Fade,
+                    );
+                } else if game.button("cancel", frame.w / 2, (frame.h as f32 / 1.4) as i32, 0.25) {
+                    self.lobby = None;
+                }
+            }
+        }
+        Change::Stay
+    }
+    fn render(&mut self, game: &mut Game) {
+        let frame = game.frame();
+        game.text(
+            "online",
+            Color::RGB(168, 181, 174),
+            frame.w / 2,
+            frame.h / 6,
+            0.5,
+        );
+        //ttf refuses to render empty text
+        if self.lobby.is_none() && !self.status.is_empty() {
+            game.text(
+                &self.status,
+                Color::RGB(146, 146, 156),
+                frame.w / 2,
+                (frame.h as f32 / 1.2) as i32,
+                0.25,
+            );
+        }
+    }
+}
+
+pub struct ControlsScreen {
+    player: usize,
+    rebinding: Option<Action>,
+}
+impl ControlsScreen {
+    pub fn new() -> Self {
+        Self {
+            player: 0,
+            rebinding: None,
+        }
+    }
+    fn save(game: &Game) {
+        if let Err(e) = game.controls.save(&game.controls_path) {
+            eprintln!("could not save controls: {}", e);
+        }
+    }
+}
+impl Scene for ControlsScreen {
+    fn update(&mut self, game: &mut Game, _dt: f32) -> Change {
+        let frame = game.frame();
+        //while waiting for a key every key is the new binding, escape too, before the back button sees it
+        if let (Some(action), Some(key)) = (self.rebinding, game.input.clicked_key()) {
+            game.controls.bind(self.player, action, key);
+            self.rebinding = None;
+            Self::save(game);
+            return Change::Stay;
+        }
+        if game.back_button() {
+            return Change::Replace(Box::new(Menu), Effect::Wipe);
+        }
+        if game.button("reset", frame.w - 12, 9, 0.25) {
+            game.controls = Controls::default();
+            self.rebinding = None;
+            Self::save(game);
+        }
+        let y = frame.h / 6 + 8;
+        if game.button("<", frame.w / 2 - 10, y, 0.25) {
+            self.player = (self.player + MAX_PLAYERS - 1) % MAX_PLAYERS;
+            self.rebinding = None;
+        }
+        if game.button(">", frame.w / 2 + 10, y, 0.25) {
+            self.player = (self.player + 1) % MAX_PLAYERS;
+            self.rebinding = None;
+        }
+        for (i, action) in Action::ALL.into_iter().enumerate() {
+            let row = y + 6 + i as i32 * 5;
+            let label = if self.rebinding == Some(action) {
+                "press a key".to_string()
+            } else {
+                game.controls.key(self.player, action).name()
+            };
+            //keys bound twice show up red
+            let color = match game.controls.conflict(self.player, action) {
+                Some(_) => Color::RGB(212, 139, 139),
+                None => game.layout.frame_color(),
+            };
+            if crate::ui::button(
+                &mut game.display,
+                &mut game.texts,
+                &label,
+                color,
+                frame.w * 2 / 3,
+                row,
+                &game.input,
+                0.25,
+            ) {
+                self.rebinding = if self.rebinding == Some(action) {
+                    None
+                } else {
+                    Some(action)
+                };
+            }
+        }
+        Change::Stay
+    }
+    fn render(&mut self, game: &mut Game) {
+        let frame = game.frame();
+        game.text(
+            "controls",
+            Color::RGB(168, 181, 174),
+            frame.w / 2,
+            frame.h / 6,
+            0.5,
+        );
+        let y = frame.h / 6 + 8;
+        game.text(
+            &format!("p{}", self.player + 1),
+            Color::RGB(241, 234, 182),
+            frame.w / 2,
+            y,
+            0.25,
+        );
+        for (i, action) in Action::ALL.into_iter().enumerate() {
+            game.text(
+                action.name(),
+                Color::RGB(146, 146, 156),
+                frame.w / 3,
+                y + 6 + i as i32 * 5,
+                0.25,
+            );
+        }
+        let clash = Action::ALL.into_iter().find_map(|action| {
+            game.controls
+                .conflict(self.player, action)
+                .map(|(player, other)| {
+                    format!(
+                        "{} clashes with p{} {}",
+                        action.name(),
+                        player + 1,
+                        other.name()
+                    )
+                })
+        });
+        if let Some(clash) = clash {
+            game.text(
+                &clash,
+                Color::RGB(212, 139, 139),
+                frame.w / 2,
+                y + 6 + Action::ALL.len() as i32 * 5,
+                0.25,
+            );
+        }
+    }
+}
+
+//pushed over whatever opened it, the menu or the pause overlay, and popped to go back
+pub struct SettingsScreen {
+    //vsync is only read when the window is created
+    restart_needed: bool,
+}
+impl SettingsScreen {
+    pub fn new() -> Self {
+        Self {
+            restart_needed: false,
+        }
+    }
+    //`<` value `>`, clicking the value steps forward too
+    fn row(game: &mut Game, label: &str, value: &str, y: i32) -> i32 {
+        let frame = game.frame();
+        game.text(label, Color::RGB(146, 146, 156), frame.w / 3, y, 0.25);
+        let mut step = 0;
+        if game.button("<", frame.w * 2 / 3 - 12, y, 0.25) {
+            step = -1;
+        }
+        //both drawn every frame, so no short circuit
+        let value = game.button(value, frame.w * 2 / 3, y, 0.25);
+        if game.button(">", frame.w * 2 / 3 + 12, y, 0.25) | value {
+            step = 1;
+        }
+        step
+    }
+}
+impl Scene for SettingsScreen {
+    fn update(&mut self, game: &mut Game, _dt: f32) -> Change {
+        if game.back_button() {
+            return Change::Pop;
+        }
+        let y = game.frame().h / 6 + 8;
+        let switch = |on: bool| if on { "on" } else { "off" };
+        //volumes go in tenths
+        let percent = |volume: f32| format!("{}%", (volume * 100.0).round());
+        let turn = |volume: f32, step: i32| {
+            ((volume * 10.0).round() + step as f32).clamp(0.0, 10.0) / 10.0
+        };
+        let mut settings = game.settings.clone();
+        let step = Self::row(game, "volume", &percent(settings.volume), y);
+        settings.volume = turn(settings.volume, step);
+        let step = Self::row(game, "music", &percent(settings.music), y + 5);
+        settings.music = turn(settings.music, step);
+        let step = Self::row(game, "sfx", &percent(settings.sfx), y + 10);
+        settings.sfx = turn(settings.sfx, step);
+        let scale = format!("{}x", settings.scale);
+        let step = Self::row(game, "window", &scale, y + 15);
+        settings.scale = cycle(&SCALES, settings.scale, step);
+        if Self::row(game, "fullscreen", switch(settings.fullscreen), y + 20) != 0 {
+            settings.fullscreen = !settings.fullscreen;
+        }
+        if Self::row(game, "vsync", switch(settings.vsync), y + 25) != 0 {
+            settings.vsync = !settings.vsync;
+            self.restart_needed = !self.restart_needed;
+        }
+        let zoom = format!("{}x", settings.zoom);
+        let step = Self::row(game, "zoom", &zoom, y + 30);
+        settings.zoom = cycle(&ZOOMS, settings.zoom, step);
+        if settings != game.settings {
+            if (settings.scale, settings.fullscreen)
+                != (game.settings.scale, game.settings.fullscreen)
+            {
+                settings.apply(&mut game.display);
+            }
+            game.audio
+                .set_volume(settings.volume, settings.music, settings.sfx);
+            game.zoom = settings.zoom;
+            game.settings = settings;
+            if let Err(e) = game.settings.save(&game.settings_path) {
+                eprintln!("could not save settings: {}", e);
+            }
+        }
+        Change::Stay
+    }
+    fn render(&mut self, game: &mut Game) {
+        let frame = game.frame();
+        game.text(
+            "settings",
+            Color::RGB(168, 181, 174),
+            frame.w / 2,
+            frame.h / 6,
+            0.5,
+        );
+        if self.restart_needed {
+            game.text(
+                "vsync changes on the next start",
+                Color::RGB(146, 146, 156),
+                frame.w / 2,
+                frame.h / 6 + 44,
+                0.25,
+            );
+        }
+    }
+}
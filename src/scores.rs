@@ -0,0 +1,126 @@
+use crate::data::{self, DataError};
+use std::path::Path;
+
+//how many runs the table keeps
+pub const KEPT: usize = 10;
+
+//one survival run, better runs reach a later wave or kill more on the way
+#[derive(Debug, Clone, PartialEq)]
+pub struct Run {
+    pub wave: usize,
+    pub kills: usize,
+    pub players: usize,
+    pub arena: String,
+}
+
+impl Run {
+    pub fn describe(&self) -> String {
+        format!(
+            "wave {}  {} kills  {} players  {}",
+            self.wave, self.kills, self.players, self.arena
+        )
+    }
+}
+
+//the best survival runs on this machine, best first
+#[derive(Debug, Clone, Default)]
+pub struct HighScores {
+    pub runs: Vec<Run>,
+}
+
+impl HighScores {
+    //a missing file means an empty table, bad lines are reported and skipped
+    pub fn load(path: &Path) -> Self {
+        let mut scores = Self::default();
+        let path = path.to_string_lossy();
+        let text = match std::fs::read_to_string(path.as_ref()) {
+            Ok(text) => text,
+            Err(_) => return scores,
+        };
+        for line in data::lines(&text) {
+            match Self::parse_line(&path, &line) {
+                Ok(run) => scores.runs.push(run),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        scores
+            .runs
+            .sort_by_key(|run| std::cmp::Reverse((run.wave, run.kills)));
+        scores.runs.truncate(KEPT);
+        scores
+    }
+    fn parse_line(path: &str, line: &data::Line) -> Result<Run, DataError> {
+        if line.key != "run" {
+            return Err(line.error(path, format!("unknown entry `{}`", line.key)));
+        }
+        line.expect_args(path, 4)?;
+        Ok(Run {
+            wave: line.num(path, 0)?,
+            kills: line.num(path, 1)?,
+            players: line.num(path, 2)?,
+            arena: line.args[3].to_string(),
+        })
+    }
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let text: String = self
+            .runs
+            .iter()
+            .map(|run| {
+                format!(
+                    "run {} {} {} {}\n",
+                    run.wave, run.kills, run.players, run.arena
+                )
+            })
+            .collect();
+        std::fs::write(path, text)
+    }
+    //where the run landed in the table, None if it did not make it
+    pub fn record(&mut self, run: Run) -> Option<usize> {
+        let rank = self
+            .runs
+            .iter()
+            .position(|r| (run.wave, run.kills) > (r.wave, r.kills))
+            .unwrap_or(self.runs.len());
+        if rank >= KEPT {
+            return None;
+        }
+        self.runs.insert(rank, run);
+        self.runs.truncate(KEPT);
+        Some(rank)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(wave: usize, kills: usize) -> Run {
+        Run {
+            wave,
+            kills,
+            players: 1,
+            arena: "jungle".to_string(),
+        }
+    }
+
+    #[test]
+    fn the_table_keeps_the_best_runs_in_order() {
+        let mut scores = HighScores::default();
+        for i in 0..KEPT + 4 {
+            //waves go up and down, kills break the ties
+            scores.record(run(i % 5, i));
+        }
+        assert_eq!(scores.runs.len(), KEPT);
+        assert!(scores
+            .runs
+            .windows(2)
+            .all(|w| (w[0].wave, w[0].kills) > (w[1].wave, w[1].kills)));
+        assert_eq!(scores.runs[0], run(4, 9));
+        assert_eq!(scores.record(run(0, 0)), None);
+        assert_eq!(scores.record(run(4, 10)), Some(0));
+        assert_eq!(scores.runs.len(), KEPT);
+    }
+}
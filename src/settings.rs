@@ -0,0 +1,131 @@
+use crate::data::{self, DataError};
+use sdl2::render::WindowCanvas;
+use sdl2::video::{FullscreenType, WindowPos};
+use std::path::Path;
+
+//the window size at scale 1, also its minimum size
+pub const WINDOW_SIZE: (u32, u32) = (776, 472);
+pub const SCALES: [f32; 3] = [1.0, 1.5, 2.0];
+pub const ZOOMS: [f32; 5] = [2.0, 3.0, 4.0, 5.0, 6.0];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    //0 to 1, the master volume scales both channels
+    pub volume: f32,
+    pub music: f32,
+    pub sfx: f32,
+    pub scale: f32,
+    pub fullscreen: bool,
+    //only read when the window is created
+    pub vsync: bool,
+    //screen pixels per arena pixel
+    pub zoom: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            music: 1.0,
+            sfx: 1.0,
+            scale: 1.0,
+            fullscreen: false,
+            vsync: true,
+            zoom: 4.0,
+        }
+    }
+}
+
+//steps through a list of options, wrapping around at both ends
+pub fn cycle(options: &[f32], current: f32, step: i32) -> f32 {
+    let i = options.iter().position(|o| *o == current).unwrap_or(0) as i32;
+    options[(i + step).rem_euclid(options.len() as i32) as usize]
+}
+
+impl Settings {
+    //a missing file means defaults, bad lines are reported and skipped
+    pub fn load(path: &Path) -> Self {
+        let mut settings = Self::default();
+        let path = path.to_string_lossy();
+        let text = match std::fs::read_to_string(path.as_ref()) {
+            Ok(text) => text,
+            Err(_) => return settings,
+        };
+        for line in data::lines(&text) {
+            if let Err(e) = settings.parse_line(&path, &line) {
+                eprintln!("{}", e);
+            }
+        }
+        settings
+    }
+    fn parse_line(&mut self, path: &str, line: &data::Line) -> Result<(), DataError> {
+        line.expect_args(path, 1)?;
+        let switch = |line: &data::Line| match line.args[0] {
+            "on" => Ok(true),
+            "off" => Ok(false),
+            other => Err(line.error(path, format!("expected `on` or `off`, found `{}`", other))),
+        };
+        match line.key {
+            "volume" => self.volume = line.num::<f32>(path, 0)?.clamp(0.0, 1.0),
+            "music" => self.music = line.num::<f32>(path, 0)?.clamp(0.0, 1.0),
+            "sfx" => self.sfx = line.num::<f32>(path, 0)?.clamp(0.0, 1.0),
+            "scale" => {
+                self.scale = line.num(path, 0)?;
+                if !SCALES.contains(&self.scale) {
+                    self.scale = 1.0;
+                    return Err(line.error(path, format!("scale must be one of {:?}", SCALES)));
+                }
+            }
+            "zoom" => {
+                self.zoom = line.num(path, 0)?;
+                if !ZOOMS.contains(&self.zoom) {
+                    self.zoom = 4.0;
+                    return Err(line.error(path, format!("zoom must be one of {:?}", ZOOMS)));
+                }
+            }
+            "fullscreen" => self.fullscreen = switch(line)?,
+            "vsync" => self.vsync = switch(line)?,
+            other => return Err(line.error(path, format!("unknown setting `{}`", other))),
+        }
+        Ok(())
+    }
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let switch = |on: bool| if on { "on" } else { "off" };
+        let text = format!(
+            "volume {}\nmusic {}\nsfx {}\nscale {}\nfullscreen {}\nvsync {}\nzoom {}\n",
+            self.volume,
+            self.music,
+            self.sfx,
+            self.scale,
+            switch(self.fullscreen),
+            switch(self.vsync),
+            self.zoom
+        );
+        std::fs::write(path, text)
+    }
+    //resizes the window, vsync and zoom are up to the caller
+    pub fn apply(&self, display: &mut WindowCanvas) {
+        let window = display.window_mut();
+        let fullscreen = if self.fullscreen {
+            FullscreenType::Desktop
+        } else {
+            FullscreenType::Off
+        };
+        if let Err(e) = window.set_fullscreen(fullscreen) {
+            eprintln!("could not change fullscreen: {}", e);
+        }
+        if !self.fullscreen {
+            let size = (
+                (WINDOW_SIZE.0 as f32 * self.scale) as u32,
+                (WINDOW_SIZE.1 as f32 * self.scale) as u32,
+            );
+            if let Err(e) = window.set_size(size.0, size.1) {
+                eprintln!("could not resize the window: {}", e);
+            }
+            window.set_position(WindowPos::Centered, WindowPos::Centered);
+        }
+    }
+}
@@ -0,0 +1,626 @@
+use crate::arena::Arena;
+use crate::components::Particle;
+use crate::creatures::Behaviour;
+use crate::entities::Wanderer;
+use crate::modes::{Field, GameMode, Mode};
+use crate::pickups::Spawner;
+use crate::player::Player;
+use rand::prelude::*;
+
+//what one player wants to do this frame, independent of where it came from
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Command {
+    pub right: bool,
+    pub left: bool,
+    pub jump: bool,
+    pub shoot: bool,
+}
+
+impl Command {
+    pub fn bits(&self) -> u8 {
+        self.right as u8 | (self.left as u8) << 1 | (self.jump as u8) << 2 | (self.shoot as u8) << 3
+    }
+    pub fn merge(&self, other: Command) -> Self {
+        Self::from_bits(self.bits() | other.bits())
+    }
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            right: bits & 1 != 0,
+            left: bits & 2 != 0,
+            jump: bits & 4 != 0,
+            shoot: bits & 8 != 0,
+        }
+    }
+}
+
+pub const MAX_PLAYERS: usize = 4;
+//best of 3 unless picked otherwise
+pub const ROUNDS: usize = 2;
+pub const ROUND_TIME: f32 = 60.0;
+//seconds the scoreboard shows between rounds
+pub const INTERMISSION: f32 = 3.0;
+
+//who takes part in a match, who may hurt whom and how long it goes on
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rules {
+    //team of every player, a match has as many players as entries
+    pub teams: Vec<usize>,
+    pub friendly_fire: bool,
+    //rounds a side has to win to take the match
+    pub rounds: usize,
+    //seconds before a round goes to sudden death, 0 never does
+    pub round_time: f32,
+    pub mode: Mode,
+}
+
+impl Rules {
+    pub fn free_for_all(players: usize) -> Self {
+        Self {
+            teams: (0..players).collect(),
+            friendly_fire: false,
+            rounds: ROUNDS,
+            round_time: ROUND_TIME,
+            mode: Mode::Deathmatch,
+        }
+    }
+    //everyone on one team against the survival waves, one long round without a clock
+    pub fn co_op(players: usize) -> Self {
+        Self {
+            teams: vec![0; players],
+            friendly_fire: false,
+            rounds: 1,
+            round_time: 0.0,
+            mode: Mode::Survival,
+        }
+    }
+    //odd players against even players
+    pub fn two_teams(players: usize, friendly_fire: bool) -> Self {
+        Self {
+            teams: (0..players).map(|i| i % 2).collect(),
+            friendly_fire,
+            rounds: ROUNDS,
+            round_time: ROUND_TIME,
+            mode: Mode::Deathmatch,
+        }
+    }
+    pub fn best_of(&self) -> usize {
+        self.rounds * 2 - 1
+    }
+    //teams are numbered from 0, in a free for all every player is one
+    pub fn sides(&self) -> usize {
+        self.teams.iter().max().map_or(0, |t| t + 1)
+    }
+    pub fn players(&self) -> usize {
+        self.teams.len()
+    }
+    pub fn team_play(&self) -> bool {
+        self.teams.iter().enumerate().any(|(i, team)| *team != i)
+    }
+    pub fn rivals(&self, a: usize, b: usize) -> bool {
+        self.teams[a] != self.teams[b]
+    }
+    pub fn can_hit(&self, shooter: usize, target: usize) -> bool {
+        shooter != target && (self.friendly_fire || self.rivals(shooter, target))
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self::free_for_all(2)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Hit { player: usize, shake: (f32, f32) },
+    Shot { player: usize },
+    Jump { player: usize },
+    Land { player: usize },
+    Splash { player: usize },
+    Death { player: usize },
+    Pickup { player: usize },
+    //`team` is None when the last ones standing went down together
+    RoundOver { team: Option<usize> },
+    //a flag was taken or brought back home
+    Flag { team: usize },
+    //a player's shot hit one of the survival creatures
+    Creature { player: usize },
+    Wave { wave: usize },
+    SuddenDeath,
+}
+
+//the whole match state, advanced by `step` without touching sdl
+#[derive(Clone)]
+pub struct Simulation {
+    pub arena: Arena,
+    pub rules: Rules,
+    pub players: Vec<Player>,
+    pub bugs: Vec<Wanderer>,
+    pub bubbles: Vec<Particle>,
+    pub pickups: Vec<Spawner>,
+    pub events: Vec<Event>,
+    pub frame: u64,
+    pub seed: u64,
+    //rounds won by each team
+    pub scores: Vec<usize>,
+    pub round: usize,
+    //seconds into the current round
+    pub clock: f32,
+    //seconds left on the scoreboard before the next round, 0 while one is being played
+    pub intermission: f32,
+    pub round_winner: Option<usize>,
+    //what the rounds are played for, picked by `rules.mode`
+    pub objective: Box<dyn GameMode>,
+    //why the mode asked for could not be played on the arena, the match is a deathmatch then
+    pub fallback: Option<String>,
+    //arenas the rounds take turns on, empty when every round is played on `arena`
+    pub rotation: Vec<Arena>,
+    rng: StdRng,
+    timer: f32,
+}
+
+//everyone back at full health on their spawn point
+fn spawn_players(arena: &Arena, rules: &Rules) -> Vec<Player> {
+    //more players than spawn points share them round robin
+    (0..rules.players())
+        .map(|i| {
+            let (x, y) = arena.spawns[i % arena.spawns.len()];
+            Player::new(x, y, &format!("p{}", i + 1), rules.teams[i])
+        })
+        .collect()
+}
+
+//the arena's creatures, back where the arena puts them
+fn spawn_bugs(arena: &Arena, rng: &mut StdRng) -> Vec<Wanderer> {
+    arena
+        .wanderers
+        .iter()
+        .map(|(kind, y)| {
+            //swimmers start somewhere in the water at that height
+            let water = arena.water.iter().find(|w| {
+                kind.behaviour == Behaviour::Swim
+                    && w.top() <= *y
+                    && *y + kind.size.1 as i32 <= w.bottom()
+            });
+            let (left, right) = match water {
+                Some(water) => (water.left(), water.right() - kind.size.0 as i32),
+                None => (arena.bounds.left(), arena.bounds.right()),
+            };
+            //water narrower than the creature still gets it, sticking out on the right
+            Wanderer::new(rng.gen_range(left..right.max(left + 1)), *y, kind, rng)
+        })
+        .collect()
+}
+
+impl Simulation {
+    pub fn new(arena: Arena, seed: u64, mut rules: Rules) -> Self {
+        //a mode the arena has no room for is played as a deathmatch, the caller tells the players
+        let fallback = rules.mode.check(&arena, rules.sides()).err();
+        if fallback.is_some() {
+            rules.mode = Mode::Deathmatch;
+        }
+        let mut rng = StdRng::seed_from_u64(seed);
+        let bugs = spawn_bugs(&arena, &mut rng);
+        Self {
+            players: spawn_players(&arena, &rules),
+            pickups: arena.spawners.clone(),
+            scores: vec![0; rules.sides()],
+            objective: rules.mode.start(&arena, rules.sides()),
+            fallback,
+            rotation: Vec::new(),
+            arena,
+            rules,
+            bugs,
+            bubbles: Vec::new(),
+            events: Vec::new(),
+            frame: 0,
+            seed,
+            round: 1,
+            clock: 0.0,
+            intermission: 0.0,
+            round_winner: None,
+            rng,
+            timer: 1.6,
+        }
+    }
+    //plays each round on the next of `arenas`, starting over after the last one
+    pub fn rotating(arenas: Vec<Arena>, seed: u64, mut rules: Rules) -> Self {
+        //every arena comes around, so every one has to fit the mode
+        let fallback = arenas
+            .iter()
+            .find_map(|arena| rules.mode.check(arena, rules.sides()).err());
+        if fallback.is_some() {
+            rules.mode = Mode::Deathmatch;
+        }
+        let mut sim = Self::new(arenas[0].clone(), seed, rules);
+        sim.fallback = fallback;
+        if arenas.len() > 1 {
+            sim.rotation = arenas;
+        }
+        sim
+    }
+    //every arena the match is played on, in the order the rounds use them
+    pub fn arenas(&self) -> Vec<Arena> {
+        if self.rotation.is_empty() {
+            vec![self.arena.clone()]
+        } else {
+            self.rotation.clone()
+        }
+    }
+    pub fn step(&mut self, dt: f32, commands: &[Command]) {
+        self.events.clear();
+        self.frame += 1;
+        self.timer += dt;
+        if self.timer > 1.6 {
+            self.timer = 0.0;
+            for water in &self.arena.water {
+                let iterations: usize = self.rng.gen_range(4..8);
+                for _i in 0..iterations {
+                    let x = self
+                        .rng
+                        .gen_range(water.left() as f32..(water.right() - 1) as f32);
+                    self.bubbles
+                        .push(Particle::new(x, (water.bottom() - 1) as f32, &mut self.rng));
+                }
+            }
+        }
+        let rng = &mut self.rng;
+        self.bubbles.retain_mut(|bubble| {
+            bubble.y -= dt * bubble.acceleration.y;
+            bubble.x += dt * rng.gen_range(-bubble.acceleration.x..bubble.acceleration.x);
+
+            bubble.alpha -= dt * 120.0;
+            bubble.alpha > 0.0
+        });
+        //a bullet is used up on the first player it hits, players blinking from
+        //a recent hit let bullets fly through
+        let players = &mut self.players;
+        let (rules, events, rng) = (&self.rules, &mut self.events, &mut self.rng);
+        for shooter in 0..players.len() {
+            let mut bullets = std::mem::take(&mut players[shooter].gun.bullets);
+            bullets.retain(|bullet| {
+                let target = (0..players.len()).find(|&target| {
+                    rules.can_hit(shooter, target)
+                        && !players[target].is_dead()
+                        && players[target].invulnerable <= 0.0
+                        && bullet.crossed(players[target].entity.rect)
+                });
+                let target = match target {
+                    Some(target) => target,
+                    None => return true,
+                };
+                events.push(Event::Hit {
+                    player: target,
+                    shake: (rng.gen_range(-4.0..4.0), rng.gen_range(-4.0..4.0)),
+                });
+                players[target].hit(bullet);
+                if players[target].is_dead() {
+                    events.push(Event::Death { player: target });
+                }
+                false
+            });
+            players[shooter].gun.bullets = bullets;
+        }
+        for (i, (player, command)) in self.players.iter_mut().zip(commands).enumerate() {
+            if player.is_dead() {
+                player.gun.bullets.clear();
+                continue;
+            }
+            let grounded = player.grounded();
+            let in_water = self.arena.in_water(player.entity.rect);
+            player.update(dt, *command, &self.arena, &mut self.rng);
+            if player.fired {
+                self.events.push(Event::Shot { player: i });
+            }
+            if player.jumped {
+                self.events.push(Event::Jump { player: i });
+            }
+            if !grounded && player.grounded() {
+                self.events.push(Event::Land { player: i });
+            }
+            if !in_water && self.arena.in_water(player.entity.rect) {
+                self.events.push(Event::Splash { player: i });
+            }
+        }
+
+        for spawner in &mut self.pickups {
+            spawner.update(dt, &mut self.rng);
+            if spawner.item.is_none() {
+                continue;
+            }
+            let rect = spawner.rect();
+            let taker = self
+                .players
+                .iter()
+                .position(|p| !p.is_dead() && p.entity.rect.has_intersection(rect));
+            if let Some(player) = taker {
+                if let Some(item) = spawner.take() {
+                    self.players[player].collect(&item);
+                    self.events.push(Event::Pickup { player });
+                }
+            }
+        }
+
+        for bug in &mut self.bugs {
+            bug.update(dt, &self.arena, &self.players, &mut self.rng);
+        }
+        if self.intermission <= 0.0 && !self.is_over() {
+            let mut field = Field {
+                arena: &self.arena,
+                players: &mut self.players,
+                events: &mut self.events,
+                rng: &mut self.rng,
+            };
+            self.objective.update(&mut field, dt);
+        }
+        self.referee(dt);
+    }
+    //keeps the round clock, scores finished rounds and starts the next one
+    fn referee(&mut self, dt: f32) {
+        if self.is_over() {
+            return;
+        }
+        if self.intermission > 0.0 {
+            self.intermission -= dt;
+            if self.intermission <= 0.0 {
+                self.next_round();
+            }
+            return;
+        }
+        let sudden_death = self.sudden_death();
+        self.clock += dt;
+        if let Some(team) = self.objective.decide(&self.players) {
+            if let Some(team) = team {
+                self.scores[team] += 1;
+            }
+            self.round_winner = team;
+            self.events.push(Event::RoundOver { team });
+            self.intermission = INTERMISSION;
+        } else if !sudden_death && self.sudden_death() {
+            //time is up: one hit ends it, shields included
+            for player in self.players.iter_mut().filter(|p| !p.is_dead()) {
+                player.entity.hp.0 = 1.0;
+                player.powers = [0.0; 3];
+            }
+            self.events.push(Event::SuddenDeath);
+        }
+    }
+    fn next_round(&mut self) {
+        self.round += 1;
+        self.clock = 0.0;
+        self.intermission = 0.0;
+        self.round_winner = None;
+        if self.rotation.is_empty() {
+            self.objective.reset();
+        } else {
+            self.arena = self.rotation[(self.round - 1) % self.rotation.len()].clone();
+            self.objective = self.rules.mode.start(&self.arena, self.rules.sides());
+        }
+        self.players = spawn_players(&self.arena, &self.rules);
+        self.pickups = self.arena.spawners.clone();
+        self.bugs = spawn_bugs(&self.arena, &mut self.rng);
+        self.bubbles.clear();
+    }
+    pub fn sudden_death(&self) -> bool {
+        self.rules.round_time > 0.0 && self.clock >= self.rules.round_time
+    }
+    //a team has won enough rounds
+    pub fn is_over(&self) -> bool {
+        self.objective.finished() || self.scores.iter().any(|score| *score >= self.rules.rounds)
+    }
+    pub fn winner(&self) -> String {
+        match self
+            .scores
+            .iter()
+            .position(|score| *score >= self.rules.rounds)
+        {
+            Some(team) => self.side_name(team),
+            None => "Tie".to_string(),
+        }
+    }
+    pub fn side_name(&self, team: usize) -> String {
+        match self.players.iter().find(|p| p.team == team) {
+            Some(player) if !self.rules.team_play() => player.title().to_string(),
+            _ => format!("team {}", team + 1),
+        }
+    }
+    //every side with its rounds won, e.g. for the scoreboard
+    pub fn standings(&self) -> Vec<(String, usize)> {
+        (0..self.scores.len())
+            .filter(|team| self.rules.teams.contains(team))
+            .map(|team| (self.side_name(team), self.scores[team]))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::{ArenaChoice, ArenaPicker};
+    use crate::entities::Bullet;
+    use crate::math::Vec2;
+    use crate::player::INVULNERABLE_TIME;
+
+    //everyone runs back and forth, jumps and shoots, each player on their own beat
+    fn scripted(frame: u64, players: usize) -> Vec<Command> {
+        (0..players)
+            .map(|i| {
+                let t = frame + i as u64 * 37;
+                Command {
+                    right: t % 240 < 120,
+                    left: t % 240 >= 120,
+                    jump: t.is_multiple_of(50),
+                    shoot: t.is_multiple_of(9),
+                }
+            })
+            .collect()
+    }
+
+    //everything two runs of the same match should agree on, down to the next random number
+    fn state(sim: &Simulation) -> Vec<String> {
+        let players = sim.players.iter().map(|p| {
+            format!(
+                "{:?} {:?} {} {}",
+                p.pos(),
+                p.entity.physics.vel,
+                p.entity.hp.0,
+                p.gun.bullets.len()
+            )
+        });
+        let bugs = sim
+            .bugs
+            .iter()
+            .map(|b| format!("{:?}", b.entity.physics.pos));
+        let score = format!(
+            "{:?} {} {} {}",
+            sim.scores,
+            sim.round,
+            sim.frame,
+            sim.rng.clone().gen::<u64>()
+        );
+        players.chain(bugs).chain(std::iter::once(score)).collect()
+    }
+
+    fn play(arena: &Arena, seed: u64, rules: Rules, frames: u64) -> Vec<String> {
+        let mut sim = Simulation::new(arena.clone(), seed, rules);
+        let players = sim.players.len();
+        for _ in 0..frames {
+            let commands = scripted(sim.frame, players);
+            sim.step(1.0 / 60.0, &commands);
+        }
+        state(&sim)
+    }
+
+    #[test]
+    fn same_seed_and_inputs_play_out_the_same() {
+        let arena = Arena::load("res/arenas/jungle.arena").unwrap();
+        let rules = Rules::free_for_all(4);
+        let first = play(&arena, 7, rules.clone(), 60 * 60);
+        assert_eq!(first, play(&arena, 7, rules.clone(), 60 * 60));
+        assert_ne!(first, play(&arena, 8, rules, 60 * 60));
+    }
+
+    #[test]
+    fn survival_plays_out_the_same() {
+        let arena = Arena::load("res/arenas/lagoon.arena").unwrap();
+        let rules = Rules::co_op(2);
+        assert_eq!(
+            play(&arena, 3, rules.clone(), 60 * 40),
+            play(&arena, 3, rules, 60 * 40)
+        );
+    }
+
+    #[test]
+    fn a_new_round_puts_the_creatures_back() {
+        let arena = Arena::load("res/arenas/jungle.arena").unwrap();
+        let mut sim = Simulation::new(arena, 5, Rules::free_for_all(2));
+        sim.bugs.clear();
+        sim.bubbles
+            .push(Particle::new(10.0, 10.0, &mut StdRng::seed_from_u64(1)));
+        sim.next_round();
+        assert_eq!(sim.bugs.len(), sim.arena.wanderers.len());
+        assert!(sim.bubbles.is_empty());
+    }
+
+    #[test]
+    fn modes_the_arena_cant_hold_become_a_deathmatch() {
+        let arena = Arena::load("res/arenas/jungle.arena").unwrap();
+        let ctf = |players| Rules {
+            mode: Mode::CaptureTheFlag,
+            ..Rules::free_for_all(players)
+        };
+        assert_eq!(
+            Simulation::new(arena.clone(), 1, ctf(2)).rules.mode,
+            Mode::CaptureTheFlag
+        );
+        assert_eq!(
+            Simulation::new(arena.clone(), 1, ctf(3)).rules.mode,
+            Mode::Deathmatch
+        );
+        let flat = Arena {
+            hill: None,
+            ..arena
+        };
+        let koth = Rules {
+            mode: Mode::KingOfTheHill,
+            ..Rules::free_for_all(2)
+        };
+        let sim = Simulation::new(flat, 1, koth);
+        assert_eq!(sim.rules.mode, Mode::Deathmatch);
+        assert!(sim.fallback.is_some());
+    }
+
+    #[test]
+    fn rotating_plays_each_round_on_the_next_arena() {
+        let mut picker = ArenaPicker::new(vec![
+            Arena::load("res/arenas/jungle.arena").unwrap(),
+            Arena::load("res/arenas/lagoon.arena").unwrap(),
+        ]);
+        picker.choice = ArenaChoice::Rotate;
+        let mut sim = Simulation::rotating(picker.pick(), 1, Rules::free_for_all(2));
+        let first = sim.arena.name.clone();
+        sim.next_round();
+        assert_ne!(sim.arena.name, first);
+        assert_eq!(sim.bugs.len(), sim.arena.wanderers.len());
+        sim.next_round();
+        assert_eq!(sim.arena.name, first);
+    }
+
+    //two players on a flat floor, p2 standing at x 30
+    fn duel() -> Simulation {
+        let text = "bounds 0 0 60 30\ntile 0 20 60 10\nspawn 10 16\nspawn 30 16\n";
+        let arena = Arena::parse("test.arena", text).unwrap();
+        Simulation::new(arena, 1, Rules::free_for_all(2))
+    }
+
+    //fired by p1 straight through p2
+    fn through_p2() -> Bullet {
+        Bullet {
+            x: 40.0,
+            y: 17.5,
+            last: Vec2::new(20.0, 17.5),
+            vel: Vec2::new(100.0, 0.0),
+            travelled: 0.0,
+            range: 1000.0,
+            damage: 2.0,
+            knockback: 0.0,
+        }
+    }
+
+    #[test]
+    fn a_bullet_hurts_once_and_is_gone() {
+        let mut sim = duel();
+        let hp = sim.players[1].entity.hp.0;
+        sim.players[0].gun.bullets.push(through_p2());
+        sim.step(1.0 / 60.0, &[Command::default(); 2]);
+        assert!(sim.players[0].gun.bullets.is_empty());
+        assert_eq!(sim.players[1].entity.hp.0, hp - 2.0);
+        sim.step(1.0 / 60.0, &[Command::default(); 2]);
+        assert_eq!(sim.players[1].entity.hp.0, hp - 2.0);
+    }
+
+    #[test]
+    fn hits_right_after_a_hit_do_nothing() {
+        let mut sim = duel();
+        let hp = sim.players[1].entity.hp.0;
+        //two at once, the second one flies through
+        sim.players[0].gun.bullets.push(through_p2());
+        sim.players[0].gun.bullets.push(through_p2());
+        sim.step(1.0 / 60.0, &[Command::default(); 2]);
+        assert_eq!(sim.players[1].entity.hp.0, hp - 2.0);
+        //and one a moment later
+        sim.players[0].gun.bullets = vec![through_p2()];
+        sim.step(INVULNERABLE_TIME / 2.0, &[Command::default(); 2]);
+        assert_eq!(sim.players[1].entity.hp.0, hp - 2.0);
+    }
+
+    #[test]
+    fn fish_fit_in_water_narrower_than_them() {
+        let text = "bounds 0 0 40 20\nspawn 1 1\nspawn 30 1\nwater 10 10 2 8\nwanderer fish 12\n";
+        let arena = Arena::parse("test.arena", text).unwrap();
+        for seed in 0..20 {
+            let sim = Simulation::new(arena.clone(), seed, Rules::free_for_all(2));
+            assert_eq!(sim.bugs[0].entity.rect.x, 10);
+        }
+    }
+}
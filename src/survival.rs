@@ -0,0 +1,242 @@
+use crate::arena::Arena;
+use crate::creatures;
+use crate::entities::Wanderer;
+use crate::modes::{Field, GameMode, View};
+use crate::player::Player;
+use crate::simulation::Event;
+use rand::prelude::*;
+use sdl2::rect::Rect;
+
+//the kind from res/creatures.cfg every wave is made of
+const CREATURE: &str = "caterpillar";
+//creatures in the first wave, every wave after brings this many more
+pub const FIRST_WAVE: usize = 3;
+pub const WAVE_GROWTH: usize = 2;
+//seconds between two creatures of a wave showing up
+const SPAWN_GAP: f32 = 0.8;
+//seconds of quiet after a wave is cleared, and before the first one
+const WAVE_BREAK: f32 = 3.0;
+const CONTACT_DAMAGE: f32 = 1.0;
+const CONTACT_PUSH: f32 = 12.0;
+
+//everyone together against waves of hostile creatures until nobody is left standing
+#[derive(Clone)]
+pub struct Survival {
+    //where creatures come from, the arena's `lair` lines or else its player spawns
+    pub lairs: Vec<(i32, i32)>,
+    pub wave: usize,
+    pub creatures: Vec<Wanderer>,
+    //creatures of this wave still to come
+    pub pending: usize,
+    pub timer: f32,
+    pub kills: usize,
+    pub fallen: bool,
+}
+
+impl Survival {
+    pub fn new(arena: &Arena) -> Self {
+        Self {
+            lairs: if arena.lairs.is_empty() {
+                arena.spawns.clone()
+            } else {
+                arena.lairs.clone()
+            },
+            wave: 0,
+            creatures: Vec::new(),
+            pending: 0,
+            timer: WAVE_BREAK,
+            kills: 0,
+            fallen: false,
+        }
+    }
+    pub fn wave_size(wave: usize) -> usize {
+        FIRST_WAVE + (wave - 1) * WAVE_GROWTH
+    }
+    //tougher and faster every wave
+    fn spawn(&mut self, field: &mut Field) {
+        let (x, y) = *self.lairs.choose(field.rng).unwrap();
+        let kind = creatures::find(CREATURE).unwrap_or(&creatures::bestiary()[0]);
+        let mut creature = Wanderer::new(x, y, kind, field.rng);
+        let hp = 1.0 + (self.wave / 2) as f32;
+        creature.hostile = true;
+        creature.entity.hp.0 = hp;
+        creature.entity.hp.1 = hp;
+        creature.entity.physics.acceleration.x = (4.0 + self.wave as f32).min(20.0);
+        self.creatures.push(creature);
+    }
+}
+
+//creatures are lower than a player's gun, shots count against a body as tall as a player
+fn body(creature: &Wanderer) -> Rect {
+    let rect = creature.entity.rect;
+    let h = rect.height().max(4);
+    Rect::new(rect.x, rect.bottom() - h as i32, rect.width(), h)
+}
+
+//the living player closest to `rect`
+fn nearest(players: &[Player], rect: Rect) -> Option<&Player> {
+    let dist = |p: &&Player| (p.entity.rect.x - rect.x).abs() + (p.entity.rect.y - rect.y).abs();
+    players.iter().filter(|p| !p.is_dead()).min_by_key(dist)
+}
+
+impl GameMode for Survival {
+    fn update(&mut self, field: &mut Field, dt: f32) {
+        if self.fallen {
+            return;
+        }
+        if field.players.iter().all(|p| p.is_dead()) {
+            self.fallen = true;
+            return;
+        }
+        if self.pending > 0 {
+            self.timer -= dt;
+            if self.timer <= 0.0 {
+                self.spawn(field);
+                self.pending -= 1;
+                self.timer = if self.pending > 0 {
+                    SPAWN_GAP
+                } else {
+                    WAVE_BREAK
+                };
+            }
+        } else if self.creatures.is_empty() {
+            self.timer -= dt;
+            if self.timer <= 0.0 {
+                self.wave += 1;
+                self.pending = Survival::wave_size(self.wave);
+                field.events.push(Event::Wave { wave: self.wave });
+            }
+        }
+
+        for creature in &mut self.creatures {
+            if let Some(target) = nearest(field.players, creature.entity.rect) {
+                let target = target.pos();
+                creature.hunt(dt, field.arena, target);
+            }
+        }
+        //a bullet is used up on the first creature it crosses
+        for (i, player) in field.players.iter_mut().enumerate() {
+            let creatures = &mut self.creatures;
+            let events = &mut *field.events;
+            player.gun.bullets.retain(|bullet| {
+                let creature = creatures
+                    .iter_mut()
+                    .find(|c| c.entity.hp.0 > 0.0 && bullet.crossed(body(c)));
+                match creature {
+                    Some(creature) => {
+                        creature.entity.hp.0 -= bullet.damage;
+                        events.push(Event::Creature { player: i });
+                        false
+                    }
+                    None => true,
+                }
+            });
+        }
+        let before = self.creatures.len();
+        self.creatures.retain(|c| c.entity.hp.0 > 0.0);
+        self.kills += before - self.creatures.len();
+
+        for creature in &self.creatures {
+            let rect = creature.entity.rect;
+            for (i, player) in field.players.iter_mut().enumerate() {
+                if player.is_dead()
+                    || player.invulnerable > 0.0
+                    || !player.entity.rect.has_intersection(rect)
+                {
+                    continue;
+                }
+                let away = (player.entity.rect.center().x - rect.center().x).signum() as f32;
+                player.hurt(CONTACT_DAMAGE, CONTACT_PUSH * away);
+                field.events.push(Event::Hit {
+                    player: i,
+                    shake: (
+                        field.rng.gen_range(-4.0..4.0),
+                        field.rng.gen_range(-4.0..4.0),
+                    ),
+                });
+                if player.is_dead() {
+                    field.events.push(Event::Death { player: i });
+                }
+            }
+        }
+    }
+    //one long round, it ends when everyone is down
+    fn decide(&self, _players: &[Player]) -> Option<Option<usize>> {
+        None
+    }
+    fn finished(&self) -> bool {
+        self.fallen
+    }
+    fn summary(&self) -> Option<String> {
+        Some(format!(
+            "reached wave {} with {} kills",
+            self.wave, self.kills
+        ))
+    }
+    fn progress(&self) -> Option<(usize, usize)> {
+        Some((self.wave, self.kills))
+    }
+    fn prey(&self, player: usize, players: &[Player]) -> Option<Rect> {
+        let mine = players[player].entity.rect;
+        self.creatures
+            .iter()
+            .map(body)
+            .min_by_key(|r| (r.x - mine.x).abs() + (r.y - mine.y).abs())
+    }
+    fn view(&self) -> View<'_> {
+        View::Waves(self)
+    }
+    fn clone_box(&self) -> Box<dyn GameMode> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_next_wave_comes_once_this_one_is_cleared() {
+        let text = "bounds 0 0 200 30\ntile 0 20 200 10\nspawn 190 16\nspawn 180 16\nlair 10 16\n";
+        let arena = Arena::parse("test.arena", text).unwrap();
+        let mut survival = Survival::new(&arena);
+        let mut players = vec![Player::new(190, 16, "p1", 0)];
+        players[0].entity.hp.0 = 1000.0;
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut events = Vec::new();
+        let mut play = |survival: &mut Survival, seconds: f32, events: &mut Vec<Event>| {
+            for _ in 0..(seconds * 60.0) as usize {
+                let mut field = Field {
+                    arena: &arena,
+                    players: &mut players,
+                    events,
+                    rng: &mut rng,
+                };
+                survival.update(&mut field, 1.0 / 60.0);
+            }
+        };
+        play(
+            &mut survival,
+            WAVE_BREAK + SPAWN_GAP * FIRST_WAVE as f32,
+            &mut events,
+        );
+        assert_eq!(survival.wave, 1);
+        assert_eq!(survival.creatures.len(), FIRST_WAVE);
+        //nothing new while the wave is still around
+        play(&mut survival, WAVE_BREAK * 2.0, &mut events);
+        assert_eq!(survival.wave, 1);
+
+        for creature in &mut survival.creatures {
+            creature.entity.hp.0 = 0.0;
+        }
+        events.clear();
+        play(&mut survival, WAVE_BREAK + 0.1, &mut events);
+        assert_eq!(survival.kills, FIRST_WAVE);
+        assert_eq!(survival.wave, 2);
+        assert_eq!(
+            survival.pending + survival.creatures.len(),
+            Survival::wave_size(2)
+        );
+        assert!(events.contains(&Event::Wave { wave: 2 }));
+    }
+}
@@ -3,14 +3,21 @@ use crate::Input;
 use sdl2::mouse::MouseButton;
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
-use sdl2::render::WindowCanvas;
+use sdl2::render::{Texture, TextureCreator, WindowCanvas};
+use sdl2::ttf::{Font, Sdl2TtfContext};
+use sdl2::video::WindowContext;
+use std::collections::HashMap;
+
+const FONT: &str = "res/font.ttf";
+const FONT_SIZE: u16 = 18;
+//labels not drawn for this many frames are dropped, and no more than MAX_TEXTS are kept
+const STALE_FRAMES: u64 = 300;
+const MAX_TEXTS: usize = 256;
 
 pub struct Layout {
     frame: Rect,
     frame_color: Color,
     bg_color: Color,
-    switching: bool,
-    state: u8,
     tabs: Vec<Rect>,
 }
 impl Layout {
@@ -18,8 +25,6 @@ impl Layout {
         Self {
             frame: Rect::new(0, 0, frame_w, frame_h),
             bg_color: Color::BLACK,
-            switching: false,
-            state: 0,
             frame_color: Color::RGB(226, 228, 223),
             tabs: vec![Rect::new(0, 0, 97, 59)],
         }
@@ -33,12 +38,6 @@ impl Layout {
     pub fn frame_color(&self) -> Color {
         self.frame_color
     }
-    pub fn state(&self) -> u8 {
-        self.state
-    }
-    pub fn switching(&self) -> bool {
-        self.switching
-    }
     pub fn render(&self, display: &mut WindowCanvas) {
         display
             .set_logical_size(self.frame.w as u32, self.frame.h as u32)
@@ -50,74 +49,166 @@ impl Layout {
             display.draw_rect(*tab).unwrap();
         }
     }
-    pub fn switch(&mut self, switch: bool, state: u8) {
-        if switch {
-            self.state = state;
+}
+
+struct CachedText<'t> {
+    texture: Texture<'t>,
+    width: u32,
+    height: u32,
+    last_used: u64,
+}
+
+//loads every font size once and keeps each rendered label as a texture,
+//keyed by text, color and size. alpha is applied when drawing so fades reuse one texture
+pub struct TextCache<'ttf, 't> {
+    ttf: &'ttf Sdl2TtfContext,
+    creator: &'t TextureCreator<WindowContext>,
+    fonts: HashMap<u16, Font<'ttf, 'static>>,
+    texts: HashMap<(String, [u8; 3], u16), CachedText<'t>>,
+    frame: u64,
+}
+
+impl<'ttf, 't> TextCache<'ttf, 't> {
+    pub fn new(ttf: &'ttf Sdl2TtfContext, creator: &'t TextureCreator<WindowContext>) -> Self {
+        Self {
+            ttf,
+            creator,
+            fonts: HashMap::new(),
+            texts: HashMap::new(),
+            frame: 0,
+        }
+    }
+    fn font(&mut self, size: u16) -> &Font<'ttf, 'static> {
+        let ttf = self.ttf;
+        self.fonts
+            .entry(size)
+            .or_insert_with(|| ttf.load_font(FONT, size).unwrap())
+    }
+    fn get(&mut self, text: &str, color: Color, size: u16) -> &mut CachedText<'t> {
+        let key = (text.to_string(), [color.r, color.g, color.b], size);
+        if !self.texts.contains_key(&key) {
+            let surf = self
+                .font(size)
+                .render(text)
+                .blended(Color::RGB(color.r, color.g, color.b))
+                .unwrap();
+            let texture = self.creator.create_texture_from_surface(&surf).unwrap();
+            self.texts.insert(
+                key.clone(),
+                CachedText {
+                    texture,
+                    width: surf.width(),
+                    height: surf.height(),
+                    last_used: 0,
+                },
+            );
+        }
+        let cached = self.texts.get_mut(&key).unwrap();
+        cached.last_used = self.frame;
+        cached
+    }
+    //call once per frame, forgets labels that stopped being drawn
+    pub fn end_frame(&mut self) {
+        self.frame += 1;
+        let frame = self.frame;
+        self.texts
+            .retain(|_, cached| cached.last_used + STALE_FRAMES > frame);
+        while self.texts.len() > MAX_TEXTS {
+            let oldest = self
+                .texts
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(key, _)| key.clone())
+                .unwrap();
+            self.texts.remove(&oldest);
+        }
+    }
+    //draws `text` scaled by `factor` with its top left at `x, y`, returns the drawn size
+    fn draw(
+        &mut self,
+        display: &mut WindowCanvas,
+        text: &str,
+        color: Color,
+        x: i32,
+        y: i32,
+        factor: f32,
+    ) -> (u32, u32) {
+        //ttf refuses to render empty text
+        if text.is_empty() {
+            return (0, 0);
+        }
+        let cached = self.get(text, color, FONT_SIZE);
+        let dim = (
+            (cached.width as f32 * factor) as u32,
+            (cached.height as f32 * factor) as u32,
+        );
+        cached.texture.set_alpha_mod(color.a);
+        display
+            .copy(&cached.texture, None, Rect::new(x, y, dim.0, dim.1))
+            .unwrap();
+        dim
+    }
+    pub fn size_of(&mut self, text: &str, factor: f32) -> (u32, u32) {
+        if text.is_empty() {
+            return (0, 0);
         }
+        //measured by the font, nothing gets rendered just to read its size
+        let (width, height) = self.font(FONT_SIZE).size_of(text).unwrap();
+        (
+            (width as f32 * factor) as u32,
+            (height as f32 * factor) as u32,
+        )
     }
 }
 
 pub fn text(
     display: &mut WindowCanvas,
+    texts: &mut TextCache,
     text: &str,
     text_color: Color,
     x: i32,
     y: i32,
     factor: f32,
 ) {
-    let ttf_ctx = sdl2::ttf::init().unwrap();
-
-    let font = ttf_ctx.load_font("res/font.ttf", 18).unwrap();
-
-    let mut dim = font.size_of(text).unwrap();
-    dim.0 = (dim.0 as f32 * factor) as u32;
-    dim.1 = (dim.1 as f32 * factor) as u32;
-    let rect = Rect::new(x - (dim.0 / 2) as i32, y - (dim.1 / 2) as i32, dim.0, dim.1);
-    let surf = font.render(text).blended(text_color).unwrap();
-    let texture_creator = display.texture_creator();
-    let texture = texture_creator.create_texture_from_surface(&surf).unwrap();
-    display.copy(&texture, None, rect).unwrap();
+    let dim = texts.size_of(text, factor);
+    texts.draw(
+        display,
+        text,
+        text_color,
+        x - (dim.0 / 2) as i32,
+        y - (dim.1 / 2) as i32,
+        factor,
+    );
 }
 
-pub fn text_ex(display: &mut WindowCanvas, texts: Vec<(&str, Color)>, x: i32, y: i32, factor: f32) {
-    let ttf_ctx = sdl2::ttf::init().unwrap();
-    let font = ttf_ctx.load_font("res/font.ttf", 18).unwrap();
+pub fn text_ex(
+    display: &mut WindowCanvas,
+    texts: &mut TextCache,
+    parts: Vec<(&str, Color)>,
+    x: i32,
+    y: i32,
+    factor: f32,
+) {
     let mut tx = x;
-    let mut ty = y;
-    for (text, color) in &texts {
-        let mut dim = font.size_of(text).unwrap();
-        dim.0 = (dim.0 as f32 * factor) as u32;
-        dim.1 = (dim.1 as f32 * factor) as u32;
-        let rect = Rect::new(tx, ty, dim.0, dim.1);
-        let surf = font.render(text).blended(*color).unwrap();
-        let texture_creator = display.texture_creator();
-        let texture = texture_creator.create_texture_from_surface(&surf).unwrap();
-        display.copy(&texture, None, rect).unwrap();
-        tx += dim.0 as i32;
+    for (text, color) in &parts {
+        tx += texts.draw(display, text, *color, tx, y, factor).0 as i32;
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn button(
     display: &mut WindowCanvas,
+    texts: &mut TextCache,
     text: &str,
-    mut text_color: Color,
+    text_color: Color,
     x: i32,
     y: i32,
     input: &Input,
     factor: f32,
 ) -> bool {
-    let ttf_ctx = sdl2::ttf::init().unwrap();
-
-    let font = ttf_ctx.load_font("res/font.ttf", 18).unwrap();
-    let mut dim = font.size_of(text).unwrap();
-    dim.0 = (dim.0 as f32 * factor) as u32;
-    dim.1 = (dim.1 as f32 * factor) as u32;
+    let dim = texts.size_of(text, factor);
     let rect = Rect::new(x - (dim.0 / 2) as i32, y - (dim.1 / 2) as i32, dim.0, dim.1);
-
-    let surf = font.render(text).blended(text_color).unwrap();
-    let texture_creator = display.texture_creator();
-    let texture = texture_creator.create_texture_from_surface(&surf).unwrap();
-    display.copy(&texture, None, rect).unwrap();
+    texts.draw(display, text, text_color, rect.x, rect.y, factor);
 
     if input.mouse_position().x > rect.x as f32
         && input.mouse_position().x < (rect.x + rect.w) as f32
@@ -0,0 +1,97 @@
+use crate::ecosystem::Ecosystem;
+use crate::input::Input;
+use crate::math::Vec2;
+use crate::replay::Replay;
+use crate::ui::{text, TextCache};
+use sdl2::{
+    keyboard::Keycode,
+    pixels::Color,
+    render::{Texture, WindowCanvas},
+};
+use std::collections::HashMap;
+
+//plays a replay back through a fresh ecosystem.
+//space pauses, right steps one frame while paused, f cycles speed and r rewinds
+pub struct Viewer {
+    pub replay: Replay,
+    pub ecosystem: Ecosystem,
+    pub paused: bool,
+    pub speed: usize,
+}
+
+impl Viewer {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            ecosystem: Ecosystem::new(replay.arenas.clone(), replay.seed, replay.rules.clone()),
+            replay,
+            paused: false,
+            speed: 1,
+        }
+    }
+    pub fn frame(&self) -> usize {
+        self.ecosystem.sim.frame as usize
+    }
+    pub fn finished(&self) -> bool {
+        self.frame() >= self.replay.frames.len()
+    }
+    pub fn rewind(&mut self) {
+        self.ecosystem = Ecosystem::new(
+            self.replay.arenas.clone(),
+            self.replay.seed,
+            self.replay.rules.clone(),
+        );
+    }
+    pub fn update(&mut self, dt: f32, input: &Input, camera: &mut Vec2) {
+        if input.is_key_clicked(Keycode::Space) {
+            self.paused = !self.paused;
+        }
+        if input.is_key_clicked(Keycode::F) {
+            self.speed = if self.speed >= 8 { 1 } else { self.speed * 2 };
+        }
+        if input.is_key_clicked(Keycode::R) {
+            self.rewind();
+            self.paused = false;
+        }
+        let steps = if !self.paused {
+            self.speed
+        } else if input.is_key_clicked(Keycode::Right) {
+            1
+        } else {
+            0
+        };
+        for _ in 0..steps {
+            if self.finished() {
+                self.paused = true;
+                break;
+            }
+            let commands = self.replay.frames[self.frame()].clone();
+            self.ecosystem.step(dt, &commands, camera);
+        }
+    }
+    pub fn render<'a>(
+        &self,
+        display: &mut WindowCanvas,
+        cache: &mut HashMap<String, Texture<'a>>,
+        texts: &mut TextCache,
+        camera: Vec2,
+        dim: (i32, i32),
+    ) {
+        self.ecosystem.render(display, cache, texts, camera);
+        self.ecosystem.render_hud(display, texts, dim);
+        text(
+            display,
+            texts,
+            &format!(
+                "{} {}/{} x{}",
+                if self.paused { "||" } else { ">" },
+                self.frame(),
+                self.replay.frames.len(),
+                self.speed
+            ),
+            Color::RGB(241, 234, 182),
+            dim.0 / 2,
+            4,
+            0.25,
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use crate::data::{self, DataError};
+use std::sync::OnceLock;
+
+const WEAPONS: &str = "res/weapons.cfg";
+//what everyone spawns holding
+pub const STARTER: &str = "pistol";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weapon {
+    pub name: String,
+    //seconds between shots
+    pub rate: f32,
+    pub damage: f32,
+    //arena pixels per second, each bullet picks a speed in this range
+    pub speed: (f32, f32),
+    //degrees either side of straight ahead
+    pub spread: f32,
+    pub pellets: u32,
+    //arena pixels a bullet flies before it is gone
+    pub range: f32,
+    pub knockback: f32,
+    //texture drawn in the player's hands
+    pub sprite: String,
+}
+
+//the gun players had before weapons came from data
+impl Default for Weapon {
+    fn default() -> Self {
+        Self {
+            name: STARTER.to_string(),
+            rate: 0.3,
+            damage: 1.0,
+            speed: (80.0, 160.0),
+            spread: 0.0,
+            pellets: 1,
+            range: 1000.0,
+            knockback: 10.0,
+            sprite: "gun".to_string(),
+        }
+    }
+}
+
+impl Weapon {
+    pub fn starter() -> Self {
+        find(STARTER).cloned().unwrap_or_default()
+    }
+}
+
+struct Armory {
+    weapons: Vec<Weapon>,
+    //of the file they came from, 0 when it could not be used
+    fingerprint: u64,
+}
+
+fn loaded() -> &'static Armory {
+    static ARMORY: OnceLock<Armory> = OnceLock::new();
+    ARMORY.get_or_init(|| {
+        let armory = data::read(WEAPONS).and_then(|text| {
+            Ok(Armory {
+                weapons: parse(WEAPONS, &text)?,
+                fingerprint: data::fingerprint(&text),
+            })
+        });
+        armory.unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            Armory {
+                weapons: vec![Weapon::default()],
+                fingerprint: 0,
+            }
+        })
+    })
+}
+
+//every weapon in res/weapons.cfg, read once
+pub fn armory() -> &'static [Weapon] {
+    &loaded().weapons
+}
+
+//which weapons.cfg this game plays with, replays recorded with another one would desync
+pub fn fingerprint() -> u64 {
+    loaded().fingerprint
+}
+
+pub fn find(name: &str) -> Option<&'static Weapon> {
+    armory().iter().find(|w| w.name == name)
+}
+
+//`weapon name` starts a weapon, the lines after it set its stats, anything left out keeps the pistol's
+#[allow(clippy::neg_cmp_op_on_partial_ord)]
+pub fn parse(path: &str, text: &str) -> Result<Vec<Weapon>, DataError> {
+    let mut weapons: Vec<Weapon> = Vec::new();
+    for line in data::lines(text) {
+        if line.key == "weapon" {
+            line.expect_args(path, 1)?;
+            weapons.push(Weapon {
+                name: line.args[0].to_string(),
+                ..Weapon::default()
+            });
+            continue;
+        }
+        let weapon = match weapons.last_mut() {
+            Some(weapon) => weapon,
+            None => {
+                return Err(line.error(path, format!("`{}` before any `weapon` line", line.key)))
+            }
+        };
+        match line.key {
+            "speed" => {
+                line.expect_args(path, 2)?;
+                weapon.speed = (line.num(path, 0)?, line.num(path, 1)?);
+                if !(weapon.speed.0 > 0.0) {
+                    return Err(line.error(path, "`speed` must be above 0".to_string()));
+                }
+                if !(weapon.speed.0 <= weapon.speed.1) {
+                    return Err(line.error(path, "`speed` expects the slowest first".to_string()));
+                }
+                continue;
+            }
+            "sprite" => {
+                line.expect_args(path, 1)?;
+                weapon.sprite = line.args[0].to_string();
+                continue;
+            }
+            _ => {}
+        }
+        line.expect_args(path, 1)?;
+        match line.key {
+            "rate" => weapon.rate = line.num(path, 0)?,
+            "damage" => weapon.damage = line.num(path, 0)?,
+            "spread" => weapon.spread = line.num(path, 0)?,
+            "pellets" => weapon.pellets = line.num(path, 0)?,
+            "range" => weapon.range = line.num(path, 0)?,
+            "knockback" => weapon.knockback = line.num(path, 0)?,
+            other => return Err(line.error(path, format!("unknown keyword `{}`", other))),
+        }
+        //values a gun can't shoot with, nan included
+        let problem = match line.key {
+            "rate" if !(weapon.rate > 0.0) => "`rate` must be above 0",
+            "range" if !(weapon.range > 0.0) => "`range` must be above 0",
+            "damage" if !(weapon.damage >= 0.0) => "`damage` can't be negative",
+            "spread" if !(weapon.spread >= 0.0) => "`spread` can't be negative",
+            "pellets" if weapon.pellets == 0 => "`pellets` can't be 0",
+            _ => continue,
+        };
+        return Err(line.error(path, problem.to_string()));
+    }
+    if weapons.is_empty() {
+        return Err(DataError::new(path, None, "no `weapon` lines".to_string()));
+    }
+    Ok(weapons)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_weapons_parse() {
+        let weapons = parse(WEAPONS, &data::read(WEAPONS).unwrap()).unwrap();
+        assert!(weapons.iter().any(|w| w.name == STARTER));
+    }
+
+    #[test]
+    fn stats_a_gun_cant_shoot_with_are_refused() {
+        let broken = [
+            "rate 0",
+            "rate -1",
+            "rate nan",
+            "range 0",
+            "range nan",
+            "spread -5",
+            "spread nan",
+            "pellets 0",
+            "speed -5 10",
+            "speed 0 10",
+            "speed nan 10",
+            "speed 10 nan",
+            "damage -1",
+            "damage nan",
+        ];
+        for stat in broken {
+            let text = format!("weapon broken\n{}\n", stat);
+            assert!(parse("test", &text).is_err(), "{} was accepted", stat);
+        }
+        assert!(parse(
+            "test",
+            "weapon fine\nrate 0.1\nspread 0\npellets 3\ndamage 0\nspeed 5 5\n"
+        )
+        .is_ok());
+    }
+}